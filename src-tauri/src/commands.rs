@@ -5,3 +5,11 @@ pub mod game_scanner;
 pub mod game_config;
 pub mod game_launcher;
 pub mod mod_manager;
+pub mod migoto_config;
+pub mod mod_variables;
+pub mod hash_remap;
+pub mod shader_fixes;
+pub mod game_session;
+pub mod diagnostics;
+pub mod game_package;
+pub mod game_detector;
\ No newline at end of file
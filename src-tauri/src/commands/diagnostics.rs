@@ -0,0 +1,300 @@
+use crate::commands::game_launcher;
+use crate::commands::game_session::HOOK_LOG_FILE;
+use crate::commands::mod_manager;
+use crate::configs::game_config::{self, get_game_install_dir, CONFIG_FILE_NAME};
+use crate::configs::app_config::AppConfig;
+use crate::utils::ini_manager::IniManager;
+use crate::utils::ini_resolver::is_disabled_name;
+use serde::Serialize;
+use std::fs::{self, File};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use tauri::{AppHandle, Manager, State};
+use zip::write::FileOptions;
+use zip::ZipWriter;
+
+const REDACTED: &str = "<redacted>";
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ModValidation {
+    relative_path: String,
+    ini_files: usize,
+    problems: Vec<String>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct BundleInfo {
+    app_version: String,
+    game_name: String,
+    created_at: u64,
+    os: String,
+    arch: String,
+    included: Vec<String>,
+    missing: Vec<String>,
+}
+
+// Enabled ini files under a mod folder (a mod can also be a single loose .ini)
+fn collect_mod_inis(path: &Path, out: &mut Vec<PathBuf>) {
+    if path.is_file() {
+        if path.extension().is_some_and(|e| e.eq_ignore_ascii_case("ini")) {
+            out.push(path.to_path_buf());
+        }
+        return;
+    }
+    let Ok(entries) = fs::read_dir(path) else { return };
+    for entry in entries.flatten() {
+        if is_disabled_name(&entry.file_name().to_string_lossy()) {
+            continue;
+        }
+        collect_mod_inis(&entry.path(), out);
+    }
+}
+
+fn validate_mod(path: &Path, relative_path: &str) -> ModValidation {
+    let mut inis = Vec::new();
+    collect_mod_inis(path, &mut inis);
+
+    let mut problems = Vec::new();
+    if inis.is_empty() {
+        problems.push("No enabled .ini file, 3Dmigoto will not load anything from this mod".to_string());
+    }
+    for ini in &inis {
+        if let Err(e) = IniManager::load(ini) {
+            problems.push(e);
+        }
+    }
+
+    ModValidation {
+        relative_path: relative_path.to_string(),
+        ini_files: inis.len(),
+        problems,
+    }
+}
+
+fn redacted_settings(config: &AppConfig) -> Result<String, String> {
+    let mut value = serde_json::to_value(config).map_err(|e| format!("Failed to serialize settings: {}", e))?;
+    if let Some(obj) = value.as_object_mut() {
+        if obj.contains_key("githubToken") {
+            obj.insert("githubToken".to_string(), serde_json::Value::String(REDACTED.to_string()));
+        }
+    }
+    serde_json::to_string_pretty(&value).map_err(|e| format!("Failed to serialize settings: {}", e))
+}
+
+// Env values can hold tokens or passwords and paths carry the user name, so
+// both are scrubbed from everything that goes into the bundle
+struct Redactor {
+    secrets: Vec<String>,
+    home: Option<String>,
+}
+
+impl Redactor {
+    fn new(home: Option<String>) -> Self {
+        Self {
+            secrets: Vec::new(),
+            home: home.filter(|h| h.len() > 1),
+        }
+    }
+
+    // Blanks every "env" map (threeDMigoto.env, launch profiles, hooks) and
+    // remembers the values so copies elsewhere are caught too. A Config.json
+    // that does not parse is left out rather than shipped unredacted.
+    fn redact_config(&mut self, content: &[u8]) -> Option<Vec<u8>> {
+        let mut value: serde_json::Value = serde_json::from_slice(content).ok()?;
+        self.redact_env_maps(&mut value);
+        serde_json::to_vec_pretty(&value).ok()
+    }
+
+    fn redact_env_maps(&mut self, value: &mut serde_json::Value) {
+        match value {
+            serde_json::Value::Object(obj) => {
+                for (key, child) in obj.iter_mut() {
+                    match child {
+                        serde_json::Value::Object(env) if key == "env" => {
+                            for v in env.values_mut() {
+                                if let Some(secret) = v.as_str().filter(|s| s.len() >= 4) {
+                                    self.secrets.push(secret.to_string());
+                                }
+                                *v = serde_json::Value::String(REDACTED.to_string());
+                            }
+                        }
+                        _ => self.redact_env_maps(child),
+                    }
+                }
+            }
+            serde_json::Value::Array(items) => items.iter_mut().for_each(|v| self.redact_env_maps(v)),
+            _ => {}
+        }
+    }
+
+    fn redact_text(&self, content: Vec<u8>) -> Vec<u8> {
+        let mut text = String::from_utf8_lossy(&content).into_owned();
+        for secret in &self.secrets {
+            text = text.replace(secret.as_str(), REDACTED);
+        }
+        if let Some(home) = &self.home {
+            // As written, with forward slashes, and with JSON-escaped backslashes
+            for variant in [home.clone(), home.replace('\\', "/"), home.replace('\\', "\\\\")] {
+                text = replace_ignore_ascii_case(&text, &variant, "<home>");
+            }
+        }
+        text.into_bytes()
+    }
+}
+
+// Windows paths differ in case between tools (C:\Users vs c:\users)
+fn replace_ignore_ascii_case(text: &str, from: &str, to: &str) -> String {
+    if from.is_empty() {
+        return text.to_string();
+    }
+    let lower_text = text.to_ascii_lowercase();
+    let lower_from = from.to_ascii_lowercase();
+    let mut out = String::with_capacity(text.len());
+    let mut last = 0;
+    for (i, _) in lower_text.match_indices(&lower_from) {
+        if i < last {
+            continue;
+        }
+        out.push_str(&text[last..i]);
+        out.push_str(to);
+        last = i + from.len();
+    }
+    out.push_str(&text[last..]);
+    out
+}
+
+fn home_dir() -> Option<String> {
+    std::env::var("USERPROFILE").or_else(|_| std::env::var("HOME")).ok()
+}
+
+fn to_json<T: Serialize>(value: &T) -> Result<String, String> {
+    serde_json::to_string_pretty(value).map_err(|e| format!("Failed to serialize diagnostics: {}", e))
+}
+
+// Zips settings, the game config, 3Dmigoto's ini and log, launch history and mod
+// checks into output_path. Returns the path written.
+#[tauri::command]
+pub async fn create_diagnostic_bundle(
+    app: AppHandle,
+    state: State<'_, Mutex<AppConfig>>,
+    game_name: String,
+    output_path: String,
+) -> Result<String, String> {
+    let settings = redacted_settings(&state.lock().unwrap().clone())?;
+    let game_dir = game_config::game_dir(&app, &game_name);
+
+    // (name in zip, contents); None marks a file that could not be collected
+    let mut entries: Vec<(String, Option<Vec<u8>>)> = vec![("settings.json".to_string(), Some(settings.into_bytes()))];
+    let read = |path: PathBuf| fs::read(path).ok();
+
+    let mut redactor = Redactor::new(home_dir());
+    let config = read(game_dir.join(CONFIG_FILE_NAME)).and_then(|c| redactor.redact_config(&c));
+    entries.push((CONFIG_FILE_NAME.to_string(), config));
+    entries.push(("PlayHistory.json".to_string(), read(game_dir.join("PlayHistory.json"))));
+    entries.push((HOOK_LOG_FILE.to_string(), read(game_dir.join(HOOK_LOG_FILE))));
+
+    match get_game_install_dir(&app, &game_name) {
+        Ok(install_dir) => {
+            entries.push(("d3dx.ini".to_string(), read(install_dir.join("d3dx.ini"))));
+            entries.push(("d3d11_log.txt".to_string(), read(install_dir.join("d3d11_log.txt"))));
+        }
+        Err(e) => {
+            println!("[Diagnostics] No 3Dmigoto folder: {}", e);
+            entries.push(("d3dx.ini".to_string(), None));
+            entries.push(("d3d11_log.txt".to_string(), None));
+        }
+    }
+
+    match mod_manager::scan_mods(app.clone(), game_name.clone()).await {
+        Ok(scan) => {
+            let mods: Vec<ModValidation> = scan
+                .mods
+                .iter()
+                .filter(|m| m.enabled)
+                .map(|m| validate_mod(Path::new(&m.path), &m.relative_path))
+                .collect();
+            entries.push(("mods.json".to_string(), Some(to_json(&mods)?.into_bytes())));
+        }
+        Err(e) => entries.push(("mods.json".to_string(), Some(to_json(&serde_json::json!({ "error": e }))?.into_bytes()))),
+    }
+
+    match game_launcher::preflight_report(&app, &game_name) {
+        Ok(report) => entries.push(("preflight.json".to_string(), Some(to_json(&report)?.into_bytes()))),
+        Err(e) => entries.push(("preflight.json".to_string(), Some(to_json(&serde_json::json!({ "error": e }))?.into_bytes()))),
+    }
+
+    let info = BundleInfo {
+        app_version: app.package_info().version.to_string(),
+        game_name: game_name.clone(),
+        created_at: std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or(0),
+        os: std::env::consts::OS.to_string(),
+        arch: std::env::consts::ARCH.to_string(),
+        included: entries.iter().filter(|(_, c)| c.is_some()).map(|(n, _)| n.clone()).collect(),
+        missing: entries.iter().filter(|(_, c)| c.is_none()).map(|(n, _)| n.clone()).collect(),
+    };
+    entries.push(("info.json".to_string(), Some(to_json(&info)?.into_bytes())));
+
+    let output = PathBuf::from(&output_path);
+    if let Some(parent) = output.parent().filter(|p| !p.as_os_str().is_empty()) {
+        fs::create_dir_all(parent).map_err(|e| format!("Failed to create output folder: {}", e))?;
+    }
+    let file = File::create(&output).map_err(|e| format!("Failed to create {:?}: {}", output, e))?;
+    let mut zip = ZipWriter::new(file);
+    let options = FileOptions::default().compression_method(zip::CompressionMethod::Deflated);
+
+    for (name, content) in entries {
+        let Some(content) = content else { continue };
+        let content = redactor.redact_text(content);
+        zip.start_file(name.as_str(), options)
+            .map_err(|e| format!("Failed to add {} to bundle: {}", name, e))?;
+        zip.write_all(&content)
+            .map_err(|e| format!("Failed to write {} to bundle: {}", name, e))?;
+    }
+    zip.finish().map_err(|e| format!("Failed to finish bundle: {}", e))?;
+
+    println!("[Diagnostics] Wrote {:?}", output);
+    Ok(output.to_string_lossy().to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn env_values_and_home_path_are_redacted() {
+        let config = serde_json::json!({
+            "basic": { "gamePreset": "SRMI" },
+            "threeDMigoto": {
+                "targetExePath": "C:\\Users\\Alex\\Games\\StarRail.exe",
+                "env": { "API_TOKEN": "hunter2-secret", "DXVK_HUD": "1" },
+                "launchProfiles": [{ "name": "Dump", "env": { "PROFILE_KEY": "profile-secret" } }],
+                "preLaunchHooks": [{ "command": "sync.exe", "env": { "HOOK_PASS": "hook-secret" } }]
+            }
+        });
+        let mut redactor = Redactor::new(Some("C:\\Users\\Alex".to_string()));
+        let redacted = redactor.redact_config(config.to_string().as_bytes()).unwrap();
+        let redacted = String::from_utf8(redactor.redact_text(redacted)).unwrap();
+
+        for secret in ["hunter2-secret", "profile-secret", "hook-secret", "Alex"] {
+            assert!(!redacted.contains(secret), "{} leaked: {}", secret, redacted);
+        }
+        let value: serde_json::Value = serde_json::from_str(&redacted).unwrap();
+        assert_eq!(value["threeDMigoto"]["env"]["DXVK_HUD"], REDACTED);
+        assert_eq!(value["threeDMigoto"]["targetExePath"], "<home>\\Games\\StarRail.exe");
+        assert_eq!(value["basic"]["gamePreset"], "SRMI");
+
+        // Copies in logs are caught too, in any case and slash style
+        let log = b"hook printed hunter2-secret from c:/users/alex/AppData\n".to_vec();
+        assert_eq!(
+            String::from_utf8(redactor.redact_text(log)).unwrap(),
+            "hook printed <redacted> from <home>/AppData\n"
+        );
+        assert!(redactor.redact_config(b"{ broken").is_none());
+    }
+}
@@ -1,46 +1,19 @@
 use crate::configs::app_config::AppConfig;
-use crate::{commands::game_scanner::BGType, utils::file_manager::get_global_games_dir};
+use crate::commands::game_scanner::{update_icon_config, BGType, GameIconSetting};
+use crate::commands::game_session::GameSessions;
+use crate::configs::game_config::{self, GameConfig};
+use crate::configs::presets;
+use crate::configs::validation::{self, FieldIssue};
+use crate::utils::file_manager::{copy_dir_recursive, get_global_games_dir};
 use serde::{Deserialize, Serialize};
 use std::fs;
 use std::io::{Cursor};
-use std::path::PathBuf;
-use tauri::{AppHandle};
-
-#[derive(Debug, Serialize, Deserialize, Clone)]
-#[serde(rename_all = "camelCase")]
-pub struct BasicSettings {
-    pub game_preset: String,
-    #[serde(default = "default_bg_type")]
-    pub background_type: BGType,
-}
-
-fn default_bg_type() -> BGType {
-    BGType::Image
-}
-
-impl Default for BasicSettings {
-    fn default() -> Self {
-        Self {
-            game_preset: "GIMI".to_string(),
-            background_type: BGType::Image,
-        }
-    }
-}
-
-#[derive(Debug, Serialize, Deserialize, Clone, Default)]
-#[serde(rename_all = "camelCase")]
-pub struct GameConfig {
-    #[serde(default)]
-    pub basic: BasicSettings,
-    #[serde(default)]
-    pub three_d_migoto: serde_json::Value,
-    #[serde(default)]
-    pub other: serde_json::Value,
-}
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use tauri::{AppHandle, Manager, State};
 
 fn get_game_config_path(app: &AppHandle, game_name: &str) -> PathBuf {
-    let games_dir = get_global_games_dir(app);
-    games_dir.join(game_name).join("Config.json")
+    game_config::config_path(app, game_name)
 }
 
 #[tauri::command]
@@ -51,14 +24,7 @@ pub fn load_game_config(app: AppHandle, game_name: String) -> Result<GameConfig,
 
     if config_path.exists() {
         println!("[GameConfig] File exists, reading...");
-        let content = fs::read_to_string(&config_path)
-            .map_err(|e| format!("Failed to read config: {}", e))?;
-
-        println!("[GameConfig] Raw content: {}", content);
-
-        let config: GameConfig =
-            serde_json::from_str(&content).map_err(|e| format!("Failed to parse config: {}", e))?;
-
+        let config = GameConfig::load_from(&config_path)?;
         println!("[GameConfig] Parsed config: {:?}", config);
         Ok(config)
     } else {
@@ -68,27 +34,35 @@ pub fn load_game_config(app: AppHandle, game_name: String) -> Result<GameConfig,
     }
 }
 
+// Field path -> message for everything wrong with the config; empty when it is fine.
+// Takes raw JSON so wrong types are reported per field too.
+#[tauri::command]
+pub fn validate_game_config(app: AppHandle, config: serde_json::Value) -> Result<Vec<FieldIssue>, String> {
+    Ok(validation::validate_value(&config, &presets::registry(&app)))
+}
+
+// strict: refuse to save a config validate_game_config would complain about
 #[tauri::command]
 pub fn save_game_config(
     app: AppHandle,
     game_name: String,
     config: GameConfig,
+    strict: Option<bool>,
 ) -> Result<(), String> {
     let config_path = get_game_config_path(&app, &game_name);
     println!("[GameConfig] Saving config for: {}", game_name);
     println!("[GameConfig] Target path: {:?}", config_path);
     println!("[GameConfig] Content to save: {:?}", config);
 
-    // Ensure parent dir exists (it should, since game exists, but just in case)
-    if let Some(parent) = config_path.parent() {
-        fs::create_dir_all(parent)
-            .map_err(|e| format!("Failed to create config directory: {}", e))?;
+    if strict.unwrap_or(false) {
+        let issues = validation::validate_config(&config, &presets::registry(&app));
+        if !issues.is_empty() {
+            let details: Vec<String> = issues.iter().map(|i| format!("{}: {}", i.field, i.message)).collect();
+            return Err(format!("Invalid config: {}", details.join("; ")));
+        }
     }
 
-    let content = serde_json::to_string_pretty(&config)
-        .map_err(|e| format!("Failed to serialize config: {}", e))?;
-
-    fs::write(&config_path, &content).map_err(|e| format!("Failed to write config: {}", e))?;
+    config.save_to(&config_path)?;
 
     println!("[GameConfig] Successfully wrote to file: {:?}", config_path);
 
@@ -101,29 +75,9 @@ pub fn create_new_config(
     new_name: String,
     config: GameConfig,
 ) -> Result<(), String> {
-    // Reuse specific path logic or just construct it
-    // We assume "Games/<new_name>/Config.json"
-
-    // Find Games dir root
-    let games_root = get_game_config_path(&app, "Dummy")
-        .parent()
-        .unwrap()
-        .parent()
-        .unwrap()
-        .to_path_buf();
-
-    let new_dir = games_root.join(&new_name);
-    if !new_dir.exists() {
-        fs::create_dir_all(&new_dir).map_err(|e| format!("Failed to create directory: {}", e))?;
-    }
-
-    let config_path = new_dir.join("Config.json");
-    let content = serde_json::to_string_pretty(&config)
-        .map_err(|e| format!("Failed to serialize config: {}", e))?;
-
-    fs::write(config_path, content).map_err(|e| format!("Failed to write config: {}", e))?;
-
-    Ok(())
+    game_config::validate_game_name(&new_name)?;
+    // save_to creates Games/<new_name>/ as needed
+    config.save_to(&get_game_config_path(&app, &new_name))
 }
 
 #[tauri::command]
@@ -138,6 +92,225 @@ pub fn delete_game_config_folder(app: AppHandle, game_name: String) -> Result<()
     Ok(())
 }
 
+// Game folder, GameIconConfig.json and the 3Dmigoto cache folder all key on the
+// name. A game that is running keeps files open in both folders.
+fn check_not_running(app: &AppHandle, game_name: &str) -> Result<(), String> {
+    if let Some(sessions) = app.try_state::<GameSessions>() {
+        if sessions.0.lock().unwrap().contains_key(game_name) {
+            return Err(format!("{} is running, close the game first", game_name));
+        }
+    }
+    Ok(())
+}
+
+// fs::rename refuses a case-only rename on Windows, so go through a temporary name.
+// Other systems are case-sensitive and rename directly.
+fn rename_dir(from: &Path, to: &Path) -> std::io::Result<()> {
+    let case_only = cfg!(windows)
+        && from != to
+        && from.to_string_lossy().eq_ignore_ascii_case(&to.to_string_lossy());
+    if case_only {
+        let temp = from.with_file_name(format!(
+            "{}.renaming",
+            from.file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_default()
+        ));
+        fs::rename(from, &temp)?;
+        if let Err(e) = fs::rename(&temp, to) {
+            let _ = fs::rename(&temp, from);
+            return Err(e);
+        }
+        return Ok(());
+    }
+    fs::rename(from, to)
+}
+
+// Whether to is an existing folder other than from; a case-only rename on a
+// case-insensitive file system finds from itself there
+fn is_other_dir(from: &Path, to: &Path) -> bool {
+    if !to.exists() {
+        return false;
+    }
+    match (fs::canonicalize(from), fs::canonicalize(to)) {
+        (Ok(a), Ok(b)) => a != b,
+        _ => true,
+    }
+}
+
+fn check_new_game_name(games_dir: &Path, old_name: &str, new_name: &str) -> Result<PathBuf, String> {
+    game_config::validate_game_name(new_name)?;
+    let new_dir = games_dir.join(new_name);
+    if is_other_dir(&games_dir.join(old_name), &new_dir) {
+        return Err(format!("A game named {} already exists", new_name));
+    }
+    Ok(new_dir)
+}
+
+// Moves Games/<old_name> and, when the config falls back to the cache dir, its
+// 3Dmigoto folder. on_renamed updates whatever else keys on the name; when it
+// fails the folders are moved back.
+fn rename_in(
+    games_dir: &Path,
+    cache_dir: &str,
+    old_name: &str,
+    new_name: &str,
+    on_renamed: impl FnOnce() -> Result<(), String>,
+) -> Result<(), String> {
+    let old_dir = games_dir.join(old_name);
+    if !old_dir.exists() {
+        return Err(format!("Game not found: {}", old_name));
+    }
+    let new_dir = check_new_game_name(games_dir, old_name, new_name)?;
+
+    // Only the cache fallback has the name in it; an explicit installDir stays put
+    let config_path = old_dir.join(game_config::CONFIG_FILE_NAME);
+    let config = if config_path.exists() { GameConfig::load_from(&config_path)? } else { GameConfig::default() };
+    let cache_move = match (
+        game_config::resolve_migoto_dir(&config.three_d_migoto, cache_dir, old_name),
+        game_config::resolve_migoto_dir(&config.three_d_migoto, cache_dir, new_name),
+    ) {
+        (Ok(from), Ok(to)) if from != to && from.exists() => Some((from, to)),
+        _ => None,
+    };
+    if let Some((from, to)) = &cache_move {
+        if is_other_dir(from, to) {
+            return Err(format!("3Dmigoto folder {:?} already exists", to));
+        }
+    }
+
+    rename_dir(&old_dir, &new_dir).map_err(|e| format!("Failed to rename game folder: {}", e))?;
+    if let Some((from, to)) = &cache_move {
+        if let Err(e) = rename_dir(from, to) {
+            let _ = rename_dir(&new_dir, &old_dir);
+            return Err(format!("Failed to rename 3Dmigoto folder: {}", e));
+        }
+    }
+
+    if let Err(e) = on_renamed() {
+        if let Some((from, to)) = &cache_move {
+            let _ = rename_dir(to, from);
+        }
+        let _ = rename_dir(&new_dir, &old_dir);
+        return Err(e);
+    }
+    Ok(())
+}
+
+fn rename_icon_entries(games_dir: &Path, from: &str, to: &str) -> Result<(), String> {
+    update_icon_config(games_dir, |icons| {
+        for entry in icons.list.iter_mut().filter(|e| e.game_name == from) {
+            entry.game_name = to.to_string();
+        }
+    })
+}
+
+#[tauri::command]
+pub fn rename_game_config(
+    app: AppHandle,
+    state: State<'_, Mutex<AppConfig>>,
+    old_name: String,
+    new_name: String,
+) -> Result<(), String> {
+    let new_name = new_name.trim().to_string();
+    if old_name == new_name {
+        return Ok(());
+    }
+    check_not_running(&app, &old_name)?;
+
+    let games_dir = get_global_games_dir(&app);
+    let mut app_config = state.lock().unwrap();
+    let cache_dir = app_config.cache_dir.clone();
+    rename_in(&games_dir, &cache_dir, &old_name, &new_name, || {
+        rename_icon_entries(&games_dir, &old_name, &new_name)?;
+        // Saved from a copy, so a failed write leaves the settings in memory as they were
+        if app_config.current_config_name == old_name {
+            let mut updated = app_config.clone();
+            updated.current_config_name = new_name.clone();
+            if let Err(e) = updated.save() {
+                let _ = rename_icon_entries(&games_dir, &new_name, &old_name);
+                return Err(e);
+            }
+            *app_config = updated;
+        }
+        Ok(())
+    })?;
+
+    println!("[GameConfig] Renamed {} to {}", old_name, new_name);
+    Ok(())
+}
+
+// Copies Games/<source_name> to Games/<new_name>, see duplicate_game_config
+fn duplicate_in(
+    games_dir: &Path,
+    cache_dir: &str,
+    source_name: &str,
+    new_name: &str,
+    copy_3dmigoto: bool,
+) -> Result<(), String> {
+    let source_dir = games_dir.join(source_name);
+    if !source_dir.exists() {
+        return Err(format!("Game not found: {}", source_name));
+    }
+    let new_dir = check_new_game_name(games_dir, "", new_name)?;
+
+    let config_path = source_dir.join(game_config::CONFIG_FILE_NAME);
+    let mut config = if config_path.exists() { GameConfig::load_from(&config_path)? } else { GameConfig::default() };
+    let uses_cache = config
+        .three_d_migoto
+        .install_dir
+        .as_deref()
+        .is_none_or(|d| d.trim().is_empty());
+    let source_migoto = game_config::resolve_migoto_dir(&config.three_d_migoto, cache_dir, source_name)
+        .ok()
+        .filter(|d| d.exists());
+
+    copy_dir_recursive(&source_dir, &new_dir).map_err(|e| format!("Failed to copy game folder: {}", e))?;
+    // Launch history belongs to the original
+    let _ = fs::remove_file(new_dir.join("PlayHistory.json"));
+
+    if let (true, Some(source_migoto)) = (uses_cache, source_migoto) {
+        if copy_3dmigoto {
+            let target = game_config::resolve_migoto_dir(&config.three_d_migoto, cache_dir, new_name)?;
+            if let Err(e) = copy_dir_recursive(&source_migoto, &target) {
+                let _ = fs::remove_dir_all(&new_dir);
+                return Err(format!("Failed to copy 3Dmigoto folder: {}", e));
+            }
+        } else {
+            config.three_d_migoto.install_dir = Some(source_migoto.to_string_lossy().to_string());
+        }
+    }
+    config.save_to(&new_dir.join(game_config::CONFIG_FILE_NAME))
+}
+
+// copy_3dmigoto: also copy a 3Dmigoto folder under the cache dir. Otherwise the
+// copy points its installDir at the original's folder and shares it.
+#[tauri::command]
+pub fn duplicate_game_config(
+    app: AppHandle,
+    source_name: String,
+    new_name: String,
+    copy_3dmigoto: Option<bool>,
+) -> Result<(), String> {
+    let new_name = new_name.trim().to_string();
+    let games_dir = get_global_games_dir(&app);
+    let cache_dir = game_config::cache_dir(&app)?;
+    duplicate_in(&games_dir, &cache_dir, &source_name, &new_name, copy_3dmigoto.unwrap_or(false))?;
+
+    update_icon_config(&games_dir, |icons| {
+        // Same visibility, favorite and categories; placed by name, never played
+        if let Some(source) = icons.get(&source_name).cloned() {
+            icons.list.push(GameIconSetting {
+                game_name: new_name.clone(),
+                order: None,
+                last_played_at: None,
+                ..source
+            });
+        }
+    })?;
+
+    println!("[GameConfig] Duplicated {} as {}", source_name, new_name);
+    Ok(())
+}
+
 #[tauri::command]
 pub fn set_game_background(
     app: AppHandle,
@@ -195,10 +368,7 @@ pub fn set_game_background(
     })?;
 
     // Update Config.json with the new type
-    let mut config =
-        load_game_config(app.clone(), game_name.clone()).unwrap_or(GameConfig::default());
-    config.basic.background_type = bg_type;
-    save_game_config(app.clone(), game_name, config)
+    GameConfig::update(&app, &game_name, |config| config.basic.background_type = bg_type)
         .map_err(|e| format!("Failed to update config: {}", e))?;
 
     Ok(())
@@ -231,13 +401,10 @@ pub async fn update_game_background(
     game_preset: String,
     bg_type: BGType,
 ) -> Result<(), String> {
-    let game_id = match game_preset.as_str() {
-        "GIMI" => "1Z8W5NHUQb",
-        "HIMI" => "osvnlOc0S8",
-        "SRMI" => "64kMb5iAWu",
-        "ZZMI" => "x6znKlJ0xK",
-        _ => return Err("Unsupported game preset for auto-update".to_string()),
-    };
+    let game_id = presets::registry(&app)
+        .get(&game_preset)
+        .and_then(|p| p.background_game_id.clone())
+        .ok_or("Unsupported game preset for auto-update")?;
 
     let url = format!("https://hyp-api.mihoyo.com/hyp/hyp-connect/api/getAllGameBasicInfo?launcher_id=jGHBHlcOq1&language=zh-cn&game_id={}", game_id);
 
@@ -342,10 +509,7 @@ pub async fn update_game_background(
     fs::write(&target_path, bytes).map_err(|e| format!("Failed to write file: {}", e))?;
 
     // Update config
-    let mut config =
-        load_game_config(app.clone(), game_name.clone()).unwrap_or(GameConfig::default());
-    config.basic.background_type = bg_type.clone();
-    save_game_config(app.clone(), game_name, config)
+    GameConfig::update(&app, &game_name, |config| config.basic.background_type = bg_type)
         .map_err(|e| format!("Failed to save config: {}", e))?;
 
     Ok(())
@@ -360,17 +524,11 @@ pub struct UpdateInfo {
 }
 
 #[tauri::command]
-pub async fn get_3dmigoto_latest_release(game_preset: String) -> Result<UpdateInfo, String> {
-    let repo = match game_preset.as_str() {
-        "GIMI" => "SilentNightSound/GIMI-Package",
-        "HIMI" => "leotorrez/HIMI-Package",
-        "SRMI" => "SpectrumQT/SRMI-Package",
-        "ZZMI" => "leotorrez/ZZMI-Package",
-        "WWMI" => "SpectrumQT/WWMI-Package",
-        "EFMI" => "SpectrumQT/EFMI-Package",
-        "AEMI" => "StarBobis/MinBase-Package",
-        _ => return Err("Unsupported game preset for package update".to_string()),
-    };
+pub async fn get_3dmigoto_latest_release(app: AppHandle, game_preset: String) -> Result<UpdateInfo, String> {
+    let repo = presets::registry(&app)
+        .get(&game_preset)
+        .and_then(|p| p.package_repo.clone())
+        .ok_or("Unsupported game preset for package update")?;
 
     let app_config = AppConfig::load().map_err(|e| e.to_string())?;
     let client = reqwest::Client::new();
@@ -466,25 +624,9 @@ pub async fn install_3dmigoto_update(
     // 1. Determine Target Directory
     let game_config = load_game_config(app.clone(), game_name.clone())?;
 
-    let target_dir: PathBuf = if let Some(dir) = game_config
-        .three_d_migoto
-        .get("installDir")
-        .and_then(|v| v.as_str())
-        .filter(|s| !s.is_empty())
-    {
-        PathBuf::from(dir)
-    } else {
-        println!("[Update] installDir not set in config, trying cache fallback...");
-        // Fallback to cache dir
-        let app_config = AppConfig::load().map_err(|e| e.to_string())?;
-        if !app_config.cache_dir.is_empty() {
-            PathBuf::from(&app_config.cache_dir)
-                .join("3Dmigoto")
-                .join(&game_name)
-        } else {
-            return Err("Cannot update: 3Dmigoto installation directory is not set, and no Cache Directory configured.".to_string());
-        }
-    };
+    let target_dir = game_config.migoto_dir(&app, &game_name).map_err(|_| {
+        "Cannot update: 3Dmigoto installation directory is not set, and no Cache Directory configured.".to_string()
+    })?;
 
     println!("[Update] Target directory detected: {:?}", target_dir);
 
@@ -555,9 +697,77 @@ pub async fn install_3dmigoto_update(
 
     // Copy Resources
     println!("[Update] Copying essential resources...");
-    crate::utils::file_manager::copy_boot_files(&app, &target_dir);
+    for problem in crate::utils::file_manager::copy_boot_files(&app, &target_dir) {
+        eprintln!("[Update] {}", problem);
+    }
 
     println!("[Update] All steps completed successfully.");
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn setup(name: &str) -> (PathBuf, PathBuf, String) {
+        let root = std::env::temp_dir().join(format!("ssmt4-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+        let games = root.join("Games");
+        let cache = root.join("Cache");
+        fs::create_dir_all(games.join("Genshin")).unwrap();
+        fs::create_dir_all(cache.join("3Dmigoto/Genshin/Mods")).unwrap();
+        GameConfig::default().save_to(&games.join("Genshin").join(game_config::CONFIG_FILE_NAME)).unwrap();
+        fs::write(games.join("Genshin/PlayHistory.json"), "[]").unwrap();
+        (root, games, cache.to_string_lossy().to_string())
+    }
+
+    #[test]
+    fn rename_moves_both_folders_and_rolls_back_when_the_follow_up_fails() {
+        let (root, games, cache) = setup("rename");
+        let migoto = |name: &str| PathBuf::from(&cache).join("3Dmigoto").join(name);
+
+        let err = rename_in(&games, &cache, "Genshin", "GI", || Err("settings write failed".to_string())).unwrap_err();
+        assert_eq!(err, "settings write failed");
+        assert!(games.join("Genshin").is_dir() && !games.join("GI").exists());
+        assert!(migoto("Genshin").join("Mods").is_dir() && !migoto("GI").exists());
+
+        rename_in(&games, &cache, "Genshin", "GI", || Ok(())).unwrap();
+        assert!(games.join("GI").join(game_config::CONFIG_FILE_NAME).is_file());
+        assert!(!games.join("Genshin").exists());
+        assert!(migoto("GI").join("Mods").is_dir() && !migoto("Genshin").exists());
+
+        fs::create_dir_all(games.join("Other")).unwrap();
+        assert!(rename_in(&games, &cache, "GI", "Other", || Ok(())).is_err());
+        if cfg!(unix) {
+            // Two games whose names differ only in case; neither is touched
+            fs::create_dir_all(games.join("gi/Mods")).unwrap();
+            assert!(rename_in(&games, &cache, "GI", "gi", || Ok(())).is_err());
+            assert!(games.join("GI").join(game_config::CONFIG_FILE_NAME).is_file());
+            assert!(games.join("gi/Mods").is_dir());
+            assert!(!games.join("GI.renaming").exists());
+        }
+        assert!(rename_in(&games, &cache, "Missing", "New", || Ok(())).is_err());
+        let _ = fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn duplicate_shares_or_copies_the_3dmigoto_folder() {
+        let (root, games, cache) = setup("duplicate");
+
+        duplicate_in(&games, &cache, "Genshin", "Shared", false).unwrap();
+        let shared = GameConfig::load_from(&games.join("Shared").join(game_config::CONFIG_FILE_NAME)).unwrap();
+        let source_migoto = PathBuf::from(&cache).join("3Dmigoto/Genshin");
+        assert_eq!(shared.three_d_migoto.install_dir, Some(source_migoto.to_string_lossy().to_string()));
+        assert!(!games.join("Shared/PlayHistory.json").exists());
+        assert!(games.join("Genshin/PlayHistory.json").exists());
+
+        duplicate_in(&games, &cache, "Genshin", "Copied", true).unwrap();
+        let copied = GameConfig::load_from(&games.join("Copied").join(game_config::CONFIG_FILE_NAME)).unwrap();
+        assert_eq!(copied.three_d_migoto.install_dir, None);
+        assert!(PathBuf::from(&cache).join("3Dmigoto/Copied/Mods").is_dir());
+
+        assert!(duplicate_in(&games, &cache, "Genshin", "Copied", false).is_err());
+        let _ = fs::remove_dir_all(&root);
+    }
+}
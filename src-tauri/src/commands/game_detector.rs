@@ -0,0 +1,176 @@
+use crate::configs::presets::{self, GamePreset, PresetExecutable};
+use serde::Serialize;
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+use tauri::AppHandle;
+
+// Walks user-chosen roots (other drives, Steam libraries, a Wine prefix's
+// drive_c) looking for the game executables each preset lists in the registry.
+
+const DEFAULT_MAX_DEPTH: usize = 6;
+// Stop after this many folders so a whole-drive root cannot stall the UI forever
+const MAX_VISITED_DIRS: usize = 200_000;
+
+// Folders that never hold a game install, or hold so much else that walking them is a waste
+const SKIPPED_DIRS: &[&str] = &[
+    "$recycle.bin",
+    "system volume information",
+    "windows",
+    "programdata",
+    "appdata",
+    "node_modules",
+    "shadercache",
+    "compatdata",
+    "mods",
+];
+
+#[derive(Debug, Serialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct InstallCandidate {
+    pub game_preset: String,
+    pub variant: String, // e.g. "Global" / "China" where the exe name tells them apart
+    pub target_exe_path: String,
+    pub game_dir: String,
+}
+
+fn is_skipped_dir(name: &str) -> bool {
+    let lower = name.to_ascii_lowercase();
+    // Unity's <Game>_Data and dot folders are large and never contain the exe
+    lower.starts_with('.') || lower.ends_with("_data") || SKIPPED_DIRS.contains(&lower.as_str())
+}
+
+fn match_exe<'a>(
+    file_name: &str,
+    parent: &Path,
+    known: &'a [(&'a str, &'a PresetExecutable)],
+) -> Option<&'a (&'a str, &'a PresetExecutable)> {
+    let parent_lower = parent.to_string_lossy().replace('\\', "/").to_ascii_lowercase();
+    known.iter().find(|(_, exe)| {
+        exe.name.eq_ignore_ascii_case(file_name)
+            && exe
+                .parent_suffix
+                .as_deref()
+                .is_none_or(|suffix| parent_lower.ends_with(&suffix.to_ascii_lowercase()))
+    })
+}
+
+pub fn detect_installs(
+    roots: &[PathBuf],
+    registry: &[GamePreset],
+    presets: Option<&[String]>,
+    max_depth: usize,
+) -> Vec<InstallCandidate> {
+    let known: Vec<(&str, &PresetExecutable)> = registry
+        .iter()
+        .filter(|p| presets.is_none_or(|wanted| wanted.iter().any(|name| name.eq_ignore_ascii_case(&p.id))))
+        .flat_map(|p| p.executables.iter().map(move |exe| (p.id.as_str(), exe)))
+        .collect();
+    if known.is_empty() {
+        return Vec::new();
+    }
+
+    let mut candidates = Vec::new();
+    let mut seen = HashSet::new();
+    let mut visited = 0;
+    // Breadth first, so shallow installs are found before the visit budget runs out
+    let mut queue: std::collections::VecDeque<(PathBuf, usize)> = roots.iter().map(|r| (r.clone(), 0)).collect();
+
+    while let Some((dir, depth)) = queue.pop_front() {
+        visited += 1;
+        if visited > MAX_VISITED_DIRS {
+            println!("[GameDetector] Stopped after {} folders", MAX_VISITED_DIRS);
+            break;
+        }
+        let Ok(entries) = fs::read_dir(&dir) else { continue };
+
+        for entry in entries.flatten() {
+            // file_type does not follow symlinks, which keeps junction loops out
+            let Ok(file_type) = entry.file_type() else { continue };
+            let name = entry.file_name().to_string_lossy().to_string();
+
+            if file_type.is_dir() {
+                if depth < max_depth && !is_skipped_dir(&name) {
+                    queue.push_back((entry.path(), depth + 1));
+                }
+            } else if file_type.is_file() {
+                let Some((preset, exe)) = match_exe(&name, &dir, &known) else { continue };
+                let path = entry.path();
+                let key = path.to_string_lossy().to_ascii_lowercase();
+                if !seen.insert(key) {
+                    continue;
+                }
+                println!("[GameDetector] Found {} at {:?}", preset, path);
+                candidates.push(InstallCandidate {
+                    game_preset: preset.to_string(),
+                    variant: exe.variant.clone(),
+                    target_exe_path: path.to_string_lossy().to_string(),
+                    game_dir: dir.to_string_lossy().to_string(),
+                });
+            }
+        }
+    }
+
+    candidates
+}
+
+// presets: only look for these (e.g. ["GIMI"]); None looks for every preset with executables.
+// max_depth: folder levels below each root, 6 by default.
+#[tauri::command]
+pub async fn detect_game_installs(
+    app: AppHandle,
+    roots: Vec<String>,
+    presets: Option<Vec<String>>,
+    max_depth: Option<usize>,
+) -> Result<Vec<InstallCandidate>, String> {
+    let roots: Vec<PathBuf> = roots
+        .iter()
+        .map(|r| r.trim())
+        .filter(|r| !r.is_empty())
+        .map(PathBuf::from)
+        .filter(|r| r.is_dir())
+        .collect();
+    if roots.is_empty() {
+        return Err("No existing folder to search".to_string());
+    }
+
+    let max_depth = max_depth.unwrap_or(DEFAULT_MAX_DEPTH);
+    let registry = presets::registry(&app);
+    tauri::async_runtime::spawn_blocking(move || {
+        detect_installs(&roots, &registry.presets, presets.as_deref(), max_depth)
+    })
+        .await
+        .map_err(|e| format!("Game detection failed: {}", e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn finds_known_exes_within_depth() {
+        let root = std::env::temp_dir().join(format!("ssmt4-detect-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+        let touch = |rel: &str| {
+            let path = root.join(rel);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, b"").unwrap();
+        };
+        touch("HoYoPlay/games/Genshin Impact game/YuanShen.exe");
+        touch("HoYoPlay/games/Genshin Impact game/YuanShen_Data/YuanShen.exe");
+        touch("Wuthering Waves/Wuthering Waves Game/Client/Binaries/Win64/Client-Win64-Shipping.exe");
+        touch("Other/Binaries/Win64/Client-Win64-Shipping.exe");
+        touch("a/b/c/d/e/f/g/StarRail.exe");
+
+        let registry = presets::parse_registry(presets::BUNDLED_PRESETS, None).unwrap();
+        let found = detect_installs(std::slice::from_ref(&root), &registry.presets, None, DEFAULT_MAX_DEPTH);
+        let mut presets: Vec<_> = found.iter().map(|c| (c.game_preset.as_str(), c.variant.as_str())).collect();
+        presets.sort();
+        assert_eq!(presets, vec![("GIMI", "China"), ("WWMI", "")]);
+
+        let only_star_rail = detect_installs(std::slice::from_ref(&root), &registry.presets, Some(&["srmi".to_string()]), 8);
+        assert_eq!(only_star_rail.len(), 1);
+        assert!(only_star_rail[0].target_exe_path.ends_with("StarRail.exe"));
+        let _ = fs::remove_dir_all(&root);
+    }
+}
@@ -1,80 +1,97 @@
-use crate::utils::file_manager::get_global_games_dir;
+use crate::commands::game_session::{self, ExitActions};
+use crate::configs::game_config::{self, get_game_install_dir, migoto_dir_for, GameConfig, ThreeDMigotoConfig};
+use crate::configs::presets::{self, GamePreset};
+use crate::utils::atomic_file;
+use crate::utils::file_manager::{self, find_bundled_resource, get_global_games_dir};
+use crate::utils::hooks;
 use crate::utils::ini_manager::IniManager;
-use serde::Deserialize;
-use std::path::PathBuf;
-use std::process::Command;
+use crate::utils::ini_resolver;
+use crate::utils::launch_backup;
+use crate::utils::launcher::{self, LaunchCommand, ProcessLauncher};
+use crate::utils::upx;
+use crate::utils::wine_stage;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
 use tauri::AppHandle;
-use sysinfo::{System, ProcessRefreshKind, UpdateKind};
 
-#[derive(Deserialize, Debug)]
+// What start_game injects: "normal", "vanilla" (no 3Dmigoto) or "noMods" (3Dmigoto, Mods folder skipped)
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
 #[serde(rename_all = "camelCase")]
-struct GameConfigFull {
-    three_d_migoto: Option<ThreeDMigotoConfig>,
+pub enum RunMode {
+    #[default]
+    Normal,
+    Vanilla,
+    NoMods,
 }
 
-#[derive(Deserialize, Debug)]
-#[serde(rename_all = "camelCase")]
-struct ThreeDMigotoConfig {
-    install_dir: Option<String>,
-    target_exe_path: Option<String>,
-    launcher_exe_path: Option<String>,
-    launch_args: Option<String>,
-    use_shell: Option<bool>,
-    show_error_popup: Option<bool>,
-    auto_set_analyse_options: Option<bool>,
-    delay: Option<i32>,
-    extra_dll: Option<String>,
-    auto_exit_seconds: Option<i32>,
-    use_upx: Option<bool>,
+// Prefix for [Include] lines commented out by a "no mods" run
+const NO_MODS_MARKER: &str = ";SSMT4_NO_MODS ";
+
+fn is_mods_include(value: &str) -> bool {
+    let value = value.trim().trim_matches('"').replace('\\', "/").to_lowercase();
+    let value = value.strip_prefix("./").unwrap_or(&value);
+    value.trim_end_matches('/') == "mods" || value.starts_with("mods/")
 }
 
-fn resolve_migoto_path(app: &AppHandle, game_name: &str, config: &ThreeDMigotoConfig) -> Result<PathBuf, String> {
-    if let Some(ref p) = config.install_dir {
-        if !p.trim().is_empty() {
-             return Ok(PathBuf::from(p));
+// Comments out the [Include] lines that pull in the Mods folder
+fn disable_mods_includes(d3dx_path: &Path) -> Result<usize, String> {
+    let _lock = atomic_file::lock(d3dx_path);
+    let mut ini = IniManager::load(d3dx_path)?;
+    let mut disabled = 0;
+    for section in ini.sections().into_iter().filter(|s| s.name.eq_ignore_ascii_case("Include")) {
+        for entry in section.entries {
+            let is_include = entry.key.eq_ignore_ascii_case("include") || entry.key.eq_ignore_ascii_case("include_recursive");
+            if is_include && is_mods_include(&entry.value) {
+                ini.set_line(entry.line, &format!("{}{} = {}", NO_MODS_MARKER, entry.key, entry.value));
+                disabled += 1;
+            }
         }
     }
-    
-    use crate::configs::app_config::AppConfig;
-    let app_config = AppConfig::load().map_err(|e| format!("Failed to load app config: {}", e))?;
-    let cache_dir = PathBuf::from(&app_config.cache_dir);
-    if app_config.cache_dir.is_empty() {
-        return Err("3Dmigoto Path not set and Cache Dir not set.".into());
+    if disabled > 0 {
+        ini.save()?;
     }
-    Ok(cache_dir.join("3Dmigoto").join(game_name))
+    Ok(disabled)
 }
 
-#[tauri::command]
-pub async fn check_3dmigoto_integrity(app: AppHandle, game_name: String) -> Result<bool, String> {
-    let games_dir = get_global_games_dir(&app);
-    let game_dir = games_dir.join(&game_name);
-    let config_path = game_dir.join("Config.json");
+// Undoes disable_mods_includes. Safe to call when nothing was disabled.
+pub(crate) fn restore_mods_includes(d3dx_path: &Path) -> Result<usize, String> {
+    if !d3dx_path.exists() {
+        return Ok(0);
+    }
+    let _lock = atomic_file::lock(d3dx_path);
+    let mut ini = IniManager::load(d3dx_path)?;
+    let marked: Vec<(usize, String)> = ini
+        .content()
+        .lines()
+        .enumerate()
+        .filter_map(|(i, line)| line.trim_start().strip_prefix(NO_MODS_MARKER).map(|rest| (i + 1, rest.to_string())))
+        .collect();
+    for (line, text) in &marked {
+        ini.set_line(*line, text);
+    }
+    if !marked.is_empty() {
+        ini.save()?;
+        println!("[GameLauncher] Restored {} Mods include line(s) in {:?}", marked.len(), d3dx_path);
+    }
+    Ok(marked.len())
+}
 
-    if !config_path.exists() {
-        return Err(format!("Config file not found: {:?}", config_path));
+fn remember_launch_profile(app: &AppHandle, game_name: &str, profile: Option<&str>) -> Result<(), String> {
+    let path = game_config::config_path(app, game_name);
+    let _lock = atomic_file::lock(&path);
+    let mut config = GameConfig::load(app, game_name)?;
+    let value = profile.unwrap_or("").to_string();
+    if config.three_d_migoto.last_launch_profile.as_deref() == Some(value.as_str()) {
+        return Ok(());
     }
+    config.three_d_migoto.last_launch_profile = Some(value);
+    config.save(app, game_name)
+}
 
-    let config_content = std::fs::read_to_string(&config_path)
-        .map_err(|e| format!("Failed to read config: {}", e))?;
-    
-    let config_full: GameConfigFull = serde_json::from_str(&config_content)
-        .map_err(|e| format!("Failed to parse config: {}", e))?;
-
-    let migoto_config = config_full.three_d_migoto.unwrap_or(ThreeDMigotoConfig {
-        install_dir: None,
-        target_exe_path: None,
-        launcher_exe_path: None,
-        launch_args: None,
-        use_shell: Some(false),
-        show_error_popup: Some(false),
-        auto_set_analyse_options: Some(false),
-        delay: None,
-        extra_dll: None,
-        auto_exit_seconds: None,
-        use_upx: None,
-    });
-
-    let migoto_path = resolve_migoto_path(&app, &game_name, &migoto_config)?;
+#[tauri::command]
+pub async fn check_3dmigoto_integrity(app: AppHandle, game_name: String) -> Result<bool, String> {
+    let migoto_path = get_game_install_dir(&app, &game_name)?;
     
     let d3d11 = migoto_path.join("d3d11.dll");
     let d3dx = migoto_path.join("d3dx.ini");
@@ -82,40 +99,453 @@ pub async fn check_3dmigoto_integrity(app: AppHandle, game_name: String) -> Resu
     Ok(d3d11.exists() && d3dx.exists())
 }
 
+#[derive(Debug, Serialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum CheckStatus {
+    Pass,
+    Warn,
+    Fail,
+}
+
+#[derive(Debug, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct PreflightCheck {
+    pub id: String,
+    pub status: CheckStatus,
+    pub message: String,
+    pub fix_hint: Option<String>,
+}
+
+#[derive(Debug, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct PreflightReport {
+    pub game_name: String,
+    pub can_launch: bool, // No check failed
+    pub checks: Vec<PreflightCheck>,
+}
+
+impl PreflightReport {
+    fn push(&mut self, id: &str, status: CheckStatus, message: impl Into<String>, fix_hint: Option<&str>) {
+        self.checks.push(PreflightCheck {
+            id: id.to_string(),
+            status,
+            message: message.into(),
+            fix_hint: fix_hint.map(|h| h.to_string()),
+        });
+    }
+
+    fn failures(&self) -> Vec<&PreflightCheck> {
+        self.checks.iter().filter(|c| c.status == CheckStatus::Fail).collect()
+    }
+}
+
+// FileVersion from the VS_FIXEDFILEINFO block of a PE file, e.g. "1.3.16.0"
+fn read_file_version(path: &Path) -> Option<String> {
+    let bytes = std::fs::read(path).ok()?;
+    const SIGNATURE: [u8; 4] = [0xBD, 0x04, 0xEF, 0xFE];
+    let pos = bytes.windows(4).position(|w| w == SIGNATURE)?;
+    let field = |offset: usize| -> Option<u32> {
+        let b = bytes.get(pos + offset..pos + offset + 4)?;
+        Some(u32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+    };
+    let ms = field(8)?;
+    let ls = field(12)?;
+    Some(format!("{}.{}.{}.{}", ms >> 16, ms & 0xFFFF, ls >> 16, ls & 0xFFFF))
+}
+
+// Installed copy of a boot file vs. the bundled one that copy_boot_files puts in place at launch.
+// A missing optional file is only a warning.
+fn check_boot_file(
+    report: &mut PreflightReport,
+    migoto_path: &Path,
+    filename: &str,
+    id: &str,
+    bundled: Option<PathBuf>,
+    required: bool,
+) {
+    let installed = migoto_path.join(filename);
+    let version_of = |p: &Path| read_file_version(p).unwrap_or_else(|| "unknown version".to_string());
+
+    match (&bundled, installed.exists()) {
+        (Some(b), false) => report.push(
+            id,
+            CheckStatus::Pass,
+            format!("{} will be copied from the bundled resources ({})", filename, version_of(b)),
+            None,
+        ),
+        (Some(b), true) => {
+            let (installed_version, bundled_version) = (version_of(&installed), version_of(b));
+            if installed_version == bundled_version {
+                report.push(id, CheckStatus::Pass, format!("{} {}", filename, installed_version), None);
+            } else {
+                report.push(
+                    id,
+                    CheckStatus::Warn,
+                    format!(
+                        "{} is {}, launching replaces it with the bundled {}",
+                        filename, installed_version, bundled_version
+                    ),
+                    Some("Back up the current file if you rely on a custom build."),
+                )
+            }
+        }
+        (None, true) => report.push(
+            id,
+            CheckStatus::Warn,
+            format!("{} {} (no bundled copy to refresh it from)", filename, version_of(&installed)),
+            None,
+        ),
+        (None, false) if required => report.push(
+            id,
+            CheckStatus::Fail,
+            format!("{} is missing and no bundled copy was found", filename),
+            Some("Reinstall SSMT4 or copy the file into the 3Dmigoto folder manually."),
+        ),
+        (None, false) => report.push(
+            id,
+            CheckStatus::Warn,
+            format!("{} is not in the 3Dmigoto folder, the system copy is used", filename),
+            Some("Copy the file into the 3Dmigoto folder if shaders fail to compile."),
+        ),
+    }
+}
+
+// Wine only loads a d3d11.dll from the game's own folder
+fn check_wine_game_dir(report: &mut PreflightReport, migoto_path: &Path, migoto_config: &ThreeDMigotoConfig) {
+    let Some(game_dir) = migoto_config.target_exe_path.as_deref().and_then(|p| Path::new(p).parent()) else {
+        return;
+    };
+    if wine_stage::has_foreign_d3d11(migoto_path, game_dir) {
+        report.push(
+            "game_d3d11",
+            CheckStatus::Warn,
+            format!("{:?} has its own d3d11.dll, Wine loads it instead of 3Dmigoto's", game_dir),
+            Some("Remove or rename the d3d11.dll in the game folder, or install 3Dmigoto there."),
+        );
+    } else if !game_dir.join("d3d11.dll").exists() {
+        report.push(
+            "game_d3d11",
+            CheckStatus::Warn,
+            "d3d11.dll is not in the game folder, it is linked there for each launch and removed after exit",
+            Some("Install 3Dmigoto into the game folder to skip the linking."),
+        );
+    } else {
+        report.push("game_d3d11", CheckStatus::Pass, game_dir.join("d3d11.dll").to_string_lossy(), None);
+    }
+}
+
+// Files that fail to parse, plus hashes overridden by more than one mod folder
+fn check_mods(report: &mut PreflightReport, migoto_path: &Path) {
+    let resolved = match ini_resolver::resolve_includes(migoto_path) {
+        Ok(r) => r,
+        Err(e) => {
+            report.push("mods_ini", CheckStatus::Warn, e, None);
+            return;
+        }
+    };
+
+    if !resolved.missing_includes.is_empty() {
+        report.push(
+            "includes",
+            CheckStatus::Warn,
+            format!("{} [Include] target(s) do not exist", resolved.missing_includes.len()),
+            Some("Fix or remove the include lines listed in the effective config view."),
+        );
+    }
+
+    let mods_dir = migoto_path.join("Mods").to_string_lossy().to_lowercase();
+    let mut unreadable = 0;
+    let mut hash_owners: HashMap<String, HashSet<String>> = HashMap::new();
+
+    for loaded in &resolved.loaded_files {
+        if !loaded.path.to_lowercase().starts_with(&mods_dir) {
+            continue;
+        }
+        let ini = match IniManager::load(Path::new(&loaded.path)) {
+            Ok(ini) => ini,
+            Err(_) => {
+                unreadable += 1;
+                continue;
+            }
+        };
+        let owner = Path::new(&loaded.path)
+            .parent()
+            .map(|p| p.to_string_lossy().to_string())
+            .unwrap_or_default();
+
+        for section in ini.sections() {
+            let name = section.name.to_lowercase();
+            if !name.starts_with("textureoverride") && !name.starts_with("shaderoverride") {
+                continue;
+            }
+            for entry in section.entries.iter().filter(|e| e.key.eq_ignore_ascii_case("hash")) {
+                hash_owners
+                    .entry(entry.value.to_lowercase())
+                    .or_default()
+                    .insert(owner.clone());
+            }
+        }
+    }
+
+    let mod_inis = resolved
+        .loaded_files
+        .iter()
+        .filter(|f| f.path.to_lowercase().starts_with(&mods_dir))
+        .count();
+    if unreadable > 0 {
+        report.push(
+            "mods_ini",
+            CheckStatus::Warn,
+            format!("{} of {} mod ini files could not be read", unreadable, mod_inis),
+            Some("Check those mods for broken or non-text ini files."),
+        );
+    } else {
+        report.push("mods_ini", CheckStatus::Pass, format!("{} mod ini files loaded", mod_inis), None);
+    }
+
+    let conflicts = hash_owners.values().filter(|owners| owners.len() > 1).count();
+    if conflicts > 0 {
+        report.push(
+            "mod_conflicts",
+            CheckStatus::Warn,
+            format!("{} hash(es) are overridden by more than one mod", conflicts),
+            Some("Disable one of the conflicting mods, or group them so only one is active."),
+        );
+    } else {
+        report.push("mod_conflicts", CheckStatus::Pass, "No conflicting hash overrides", None);
+    }
+}
+
+fn build_preflight_report(app: &AppHandle, game_name: &str, migoto_config: &ThreeDMigotoConfig) -> PreflightReport {
+    let mut report = PreflightReport {
+        game_name: game_name.to_string(),
+        can_launch: true,
+        checks: Vec::new(),
+    };
+
+    // Target exe
+    match migoto_config.target_exe_path.as_deref().filter(|p| !p.trim().is_empty()) {
+        None => report.push(
+            "target_exe",
+            CheckStatus::Fail,
+            "Target exe is not configured",
+            Some("Set the game executable in the game settings."),
+        ),
+        Some(p) if !Path::new(p).exists() => report.push(
+            "target_exe",
+            CheckStatus::Fail,
+            format!("Target exe does not exist: {}", p),
+            Some("Check the path, the game may have moved or been updated."),
+        ),
+        Some(p) => report.push("target_exe", CheckStatus::Pass, p, None),
+    }
+
+    // Launcher exe (optional)
+    match migoto_config.launcher_exe_path.as_deref().filter(|p| !p.trim().is_empty()) {
+        None => report.push("launcher_exe", CheckStatus::Pass, "Not set, the target exe is started directly", None),
+        Some(p) if !Path::new(p).exists() => report.push(
+            "launcher_exe",
+            CheckStatus::Fail,
+            format!("Launcher exe does not exist: {}", p),
+            Some("Fix the launcher path or clear it to start the game exe directly."),
+        ),
+        Some(p) => report.push("launcher_exe", CheckStatus::Pass, p, None),
+    }
+
+    // Launch args
+    if let Err(e) = launcher::split_args(migoto_config.launch_args.as_deref().unwrap_or_default()) {
+        report.push("launch_args", CheckStatus::Fail, e, Some("Close the quote or remove it from the launch arguments."));
+    }
+
+    // Launch mode
+    let launcher = match launcher::create_launcher(
+        migoto_config.launch_mode.as_deref(),
+        migoto_config.wine_binary.as_deref(),
+        migoto_config.wine_prefix.as_deref(),
+    ) {
+        Ok(l) => {
+            report.push("launch_mode", CheckStatus::Pass, format!("{} launcher", l.name()), None);
+            Some(l)
+        }
+        Err(e) => {
+            report.push("launch_mode", CheckStatus::Fail, e, Some("Review the launch mode and Wine/Proton settings."));
+            None
+        }
+    };
+
+    // 3Dmigoto install dir
+    let migoto_path = match migoto_dir_for(app, game_name, migoto_config) {
+        Ok(p) if p.is_dir() => {
+            report.push("install_dir", CheckStatus::Pass, p.to_string_lossy(), None);
+            p
+        }
+        Ok(p) => {
+            report.push(
+                "install_dir",
+                CheckStatus::Fail,
+                format!("3Dmigoto folder does not exist: {:?}", p),
+                Some("Set the 3Dmigoto install folder in the game settings."),
+            );
+            report.can_launch = false;
+            return report;
+        }
+        Err(e) => {
+            report.push("install_dir", CheckStatus::Fail, e, Some("Set the 3Dmigoto install folder or the cache folder."));
+            report.can_launch = false;
+            return report;
+        }
+    };
+
+    // d3dx.ini
+    let d3dx_path = migoto_path.join("d3dx.ini");
+    if !d3dx_path.exists() {
+        report.push(
+            "d3dx_ini",
+            CheckStatus::Fail,
+            "d3dx.ini not found",
+            Some("Install a 3Dmigoto package for this game into the install folder."),
+        );
+    } else {
+        match IniManager::load(&d3dx_path) {
+            Ok(ini) => report.push(
+                "d3dx_ini",
+                CheckStatus::Pass,
+                format!("d3dx.ini ({})", ini.encoding_name()),
+                None,
+            ),
+            Err(e) => report.push("d3dx_ini", CheckStatus::Fail, e, Some("Repair or replace d3dx.ini.")),
+        }
+    }
+
+    // Loader and DLLs
+    let boot_file = |report: &mut PreflightReport, filename: &str, id: &str| {
+        let required = file_manager::BOOT_FILES.iter().any(|(f, r)| *f == filename && *r);
+        check_boot_file(report, &migoto_path, filename, id, find_bundled_resource(app, filename), required);
+    };
+    if launcher.as_ref().is_none_or(|l| l.uses_run_exe()) {
+        boot_file(&mut report, "Run.exe", "run_exe");
+    } else {
+        report.push("run_exe", CheckStatus::Pass, "Not used, d3d11.dll is loaded through a DLL override", None);
+        check_wine_game_dir(&mut report, &migoto_path, migoto_config);
+    }
+    boot_file(&mut report, "d3d11.dll", "d3d11_dll");
+    boot_file(&mut report, "d3dcompiler_47.dll", "d3dcompiler");
+
+    // UPX
+    if migoto_config.use_upx.unwrap_or(false) {
+        match find_bundled_resource(app, "upx.exe") {
+            Some(p) => report.push("upx", CheckStatus::Pass, p.to_string_lossy(), None),
+            None => report.push(
+                "upx",
+                CheckStatus::Warn,
+                "UPX packing is enabled but upx.exe was not found, d3d11.dll will not be packed",
+                Some("Reinstall SSMT4 or turn off UPX packing."),
+            ),
+        }
+    } else {
+        report.push("upx", CheckStatus::Pass, "UPX packing is disabled", None);
+    }
+
+    // Mods
+    let mods_path = migoto_path.join("Mods");
+    if mods_path.is_dir() {
+        report.push("mods_dir", CheckStatus::Pass, mods_path.to_string_lossy(), None);
+    } else {
+        report.push(
+            "mods_dir",
+            CheckStatus::Warn,
+            "Mods folder does not exist, the game starts without mods",
+            Some("Create a Mods folder in the 3Dmigoto install folder."),
+        );
+    }
+    if d3dx_path.exists() {
+        check_mods(&mut report, &migoto_path);
+    }
+
+    report.can_launch = report.failures().is_empty();
+    report
+}
+
+// Report for the remembered launch profile, used outside of a launch
+pub(crate) fn preflight_report(app: &AppHandle, game_name: &str) -> Result<PreflightReport, String> {
+    let (migoto_config, _) = GameConfig::load(app, game_name)?.three_d_migoto.resolve_profile(None)?;
+    Ok(build_preflight_report(app, game_name, &migoto_config))
+}
+
 #[tauri::command]
-pub async fn start_game(app: AppHandle, game_name: String) -> Result<(), String> {
-    let games_dir = get_global_games_dir(&app);
-    let game_dir = games_dir.join(&game_name);
-    let config_path = game_dir.join("Config.json");
+pub async fn preflight_launch(
+    app: AppHandle,
+    game_name: String,
+    profile_name: Option<String>,
+) -> Result<PreflightReport, String> {
+    let (migoto_config, _) = GameConfig::load(&app, &game_name)?.three_d_migoto.resolve_profile(profile_name.as_deref())?;
+    Ok(build_preflight_report(&app, &game_name, &migoto_config))
+}
+
+#[derive(Debug, Serialize, Clone, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct LaunchResult {
+    pub profile: Option<String>,
+    pub warnings: Vec<LaunchWarning>, // Problems that did not stop the launch
+}
+
+// The frontend shows t("home.launchwarning." + key, { detail })
+#[derive(Debug, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct LaunchWarning {
+    pub key: String,
+    pub detail: String, // English, from the error that caused it
+}
 
-    if !config_path.exists() {
-        return Err(format!("Config file not found: {:?}", config_path));
+impl LaunchWarning {
+    fn new(key: &str, detail: impl Into<String>) -> Self {
+        Self {
+            key: key.to_string(),
+            detail: detail.into(),
+        }
     }
+}
 
-    let config_content = std::fs::read_to_string(&config_path)
-        .map_err(|e| format!("Failed to read config: {}", e))?;
-    
-    // Log the config content to verify structure if needed (optional)
-    // println!("[GameLauncher] Config Content: {}", config_content);
-
-    let config_full: GameConfigFull = serde_json::from_str(&config_content)
-        .map_err(|e| format!("Failed to parse config: {}", e))?;
-
-    let migoto_config = config_full.three_d_migoto.unwrap_or(ThreeDMigotoConfig {
-        install_dir: None,
-        target_exe_path: None,
-        launcher_exe_path: None,
-        launch_args: None,
-        use_shell: Some(false),
-        show_error_popup: Some(false),
-        auto_set_analyse_options: Some(false),
-        delay: None,
-        extra_dll: None,
-        auto_exit_seconds: None,
-        use_upx: None,
-    });
-
-    let migoto_path = resolve_migoto_path(&app, &game_name, &migoto_config)?;
+fn pack_d3d11(app: &AppHandle, migoto_path: &Path) -> Result<(), String> {
+    let d3d11_path = migoto_path.join("d3d11.dll");
+    if !d3d11_path.exists() {
+        return Err("d3d11.dll not found".to_string());
+    }
+    let upx_exe = find_bundled_resource(app, "upx.exe").ok_or("upx.exe not found")?;
+    upx::pack_cached(&upx_exe, &upx::cache_dir(&get_global_games_dir(app)), &d3d11_path)?;
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn unpack_d3d11(app: AppHandle, game_name: String) -> Result<(), String> {
+    let migoto_path = get_game_install_dir(&app, &game_name)?;
+    let d3d11_path = migoto_path.join("d3d11.dll");
+    if !d3d11_path.exists() {
+        return Err(format!("d3d11.dll not found at {:?}", d3d11_path));
+    }
+
+    let upx_exe = find_bundled_resource(&app, "upx.exe").ok_or("upx.exe not found")?;
+    upx::unpack(&upx_exe, &d3d11_path)
+}
+
+#[tauri::command]
+pub async fn start_game(
+    app: AppHandle,
+    game_name: String,
+    profile_name: Option<String>,
+    run_mode: Option<RunMode>,
+) -> Result<LaunchResult, String> {
+    let run_mode = run_mode.unwrap_or_default();
+    let mut warnings = Vec::new();
+    let game_dir = game_config::game_dir(&app, &game_name);
+    let config = GameConfig::load(&app, &game_name)?;
+
+    let (migoto_config, profile) = config.three_d_migoto.resolve_profile(profile_name.as_deref())?;
+    if let Some(name) = &profile {
+        println!("[GameLauncher] Using launch profile '{}'", name);
+    }
+
+    let migoto_path = migoto_dir_for(&app, &game_name, &migoto_config)?;
     
     // Check if target_exe_path is set and exists
     let target_exe = migoto_config.target_exe_path.as_deref().unwrap_or("");
@@ -129,63 +559,149 @@ pub async fn start_game(app: AppHandle, game_name: String) -> Result<(), String>
         return Err(format!("配置的游戏进程文件不存在: {}\n请检查路径是否正确。", target_exe));
     }
 
-    // Copy essential boot files
-    crate::utils::file_manager::copy_boot_files(&app, &migoto_path);
+    // Pre-launch hooks run before anything in the 3Dmigoto folder is touched
+    let hook_log = game_dir.join(game_session::HOOK_LOG_FILE);
+    if let Some(pre_hooks) = &migoto_config.pre_launch_hooks {
+        let results = hooks::run_hooks(pre_hooks, "pre-launch", &hook_log)
+            .await
+            .map_err(|e| format!("Launch aborted by pre-launch hook: {}", e))?;
+        warnings.extend(
+            results
+                .iter()
+                .filter(|r| !r.success)
+                .map(|r| LaunchWarning::new("hookfailed", r.failure_summary())),
+        );
+    }
+
+    let exit_actions = ExitActions {
+        post_exit_hooks: migoto_config.post_exit_hooks.clone().unwrap_or_default(),
+        ..Default::default()
+    };
+
+    let launcher = launcher::create_launcher(
+        migoto_config.launch_mode.as_deref(),
+        migoto_config.wine_binary.as_deref(),
+        migoto_config.wine_prefix.as_deref(),
+    )?;
+    println!("[GameLauncher] Using {} launcher ({:?})", launcher.name(), run_mode);
+
+    // Vanilla: the 3Dmigoto folder is left alone entirely
+    if run_mode == RunMode::Vanilla {
+        launch_vanilla(launcher.as_ref(), &migoto_config)?;
+        game_session::start_session_monitor(app.clone(), game_name, target_path, exit_actions);
+        return Ok(LaunchResult { profile, warnings });
+    }
+
+    let d3dx_path = migoto_path.join("d3dx.ini");
+    undo_leftover_session(&migoto_path, game_session::is_monitored(&app, &game_name))?;
+
+    // Refuse to touch the 3Dmigoto folder when something is known to be broken
+    let report = build_preflight_report(&app, &game_name, &migoto_config);
+    let failures = report.failures();
+    if !failures.is_empty() {
+        let lines: Vec<String> = failures
+            .iter()
+            .map(|c| match &c.fix_hint {
+                Some(hint) => format!("- {}: {} ({})", c.id, c.message, hint),
+                None => format!("- {}: {}", c.id, c.message),
+            })
+            .collect();
+        return Err(format!("Launch preflight failed:\n{}", lines.join("\n")));
+    }
+
+    let mut exit_actions = exit_actions;
+    if migoto_config.restore_after_exit.unwrap_or(false) {
+        launch_backup::take_backup(&migoto_path)?;
+        exit_actions.restore_backup = Some(migoto_path.clone());
+    }
+
+    // Copy essential boot files; what is already installed is used if that fails
+    warnings.extend(
+        file_manager::copy_boot_files(&app, &migoto_path)
+            .into_iter()
+            .map(|problem| LaunchWarning::new("bootfile", problem)),
+    );
     
-    // UPX Packing (after copying files)
+    // UPX Packing (after copying files). A failed pack leaves the plain dll in place.
     if migoto_config.use_upx.unwrap_or(false) {
-        println!("[GameLauncher] UPX packing enabled. Packing d3d11.dll...");
-        let d3d11_path = migoto_path.join("d3d11.dll");
-        if d3d11_path.exists() {
-             use tauri::Manager;
-             let resource_dir = app.path().resource_dir().map_err(|e| format!("Failed to get resource dir for UPX: {}", e))?;
-             let upx_exe = resource_dir.join("upx.exe");
-             
-             // Check if upx.exe exists
-             let mut upx_to_use = upx_exe.clone();
-             if !upx_to_use.exists() {
-                 let dev_upx = PathBuf::from("resources").join("upx.exe");
-                 if dev_upx.exists() {
-                     upx_to_use = dev_upx; 
-                 } else {
-                     println!("[GameLauncher] Warning: upx.exe not found. Skipping packing.");
-                 }
-             }
-
-             if upx_to_use.exists() {
-                  println!("[GameLauncher] Running UPX: {:?} {:?}", upx_to_use, d3d11_path);
-                  
-                  // Execute UPX: upx.exe d3d11.dll
-                  let output = Command::new(&upx_to_use)
-                    .arg(&d3d11_path)
-                    .output()
-                    .map_err(|e| format!("Failed to execute UPX process: {}", e))?;
-                 
-                 let stdout = String::from_utf8_lossy(&output.stdout);
-                 let stderr = String::from_utf8_lossy(&output.stderr);
-                 
-                 println!("[GameLauncher] UPX stdout: {}", stdout);
-                 if !stderr.is_empty() {
-                     println!("[GameLauncher] UPX stderr: {}", stderr);
-                 }
-
-                 if !output.status.success() {
-                     println!("[GameLauncher] UPX failed with exit code: {:?}", output.status.code());
-                 } else {
-                     println!("[GameLauncher] UPX packing successful.");
-                 }
-             }
+        if let Err(e) = pack_d3d11(&app, &migoto_path) {
+            eprintln!("[GameLauncher] UPX packing failed: {}", e);
+            warnings.push(LaunchWarning::new("upxpackfailed", e));
+        }
+    }
+
+    if run_mode == RunMode::NoMods {
+        let disabled = disable_mods_includes(&d3dx_path)?;
+        println!("[GameLauncher] No mods: disabled {} Mods include line(s)", disabled);
+        exit_actions.restore_mods_include = Some(d3dx_path.clone());
+    }
+
+    if !launcher.uses_run_exe() {
+        exit_actions.unstage_wine = wine_game_dir(&migoto_config);
+    }
+
+    let preset = presets::registry(&app).get_or_shared(&config.basic.game_preset);
+    if let Err(e) = run_launch_sequence(launcher.as_ref(), &migoto_path, &migoto_config, &preset).await {
+        if let Some(game_dir) = &exit_actions.unstage_wine {
+            let _ = wine_stage::unstage(game_dir);
+        }
+        if exit_actions.restore_backup.is_some() {
+            let _ = launch_backup::restore_backup(&migoto_path);
         }
+        if exit_actions.restore_mods_include.is_some() {
+            let _ = restore_mods_includes(&d3dx_path);
+        }
+        return Err(e);
+    }
+
+    if profile_name.is_some() {
+        if let Err(e) = remember_launch_profile(&app, &game_name, profile.as_deref()) {
+            eprintln!("[GameLauncher] Failed to remember launch profile: {}", e);
+        }
+    }
+
+    game_session::start_session_monitor(app.clone(), game_name, target_path, exit_actions);
+
+    Ok(LaunchResult { profile, warnings })
+}
+
+// Undoes a session that was never cleaned up (e.g. SSMT4 closed mid-session).
+// A backup that cannot be restored yet is kept and retried next time. While the
+// game is still running its session owns these changes and undoes them on exit.
+fn undo_leftover_session(migoto_path: &Path, session_running: bool) -> Result<(), String> {
+    if session_running {
+        println!("[GameLauncher] Game is still running, leaving its session changes in place");
+        return Ok(());
     }
+    if let Err(e) = launch_backup::restore_backup(migoto_path) {
+        eprintln!("[GameLauncher] {}", e);
+    }
+    restore_mods_includes(&migoto_path.join("d3dx.ini"))?;
+    Ok(())
+}
 
+// Writes the launch settings into d3dx.ini
+fn apply_d3dx_settings(migoto_path: &Path, migoto_config: &ThreeDMigotoConfig, preset: &GamePreset) -> Result<(), String> {
     let d3dx_path = migoto_path.join("d3dx.ini");
     if !d3dx_path.exists() {
         return Err(format!("d3dx.ini not found at {:?}", d3dx_path));
     }
 
     // Load INI
+    let _lock = atomic_file::lock(&d3dx_path);
     let mut ini = IniManager::load(&d3dx_path)?;
 
+    // 0. Preset defaults ([Hunting] hunting, marking_actions, ...); the game's own settings below win.
+    // The preset's delay is only a suggestion, d3dx.ini keeps its own unless the user set one (step 5)
+    for (section, keys) in &preset.d3dx {
+        for (key, value) in keys {
+            if section == "System" && key == "dll_initialization_delay" {
+                continue;
+            }
+            ini.set(section, key, value);
+        }
+    }
+
     // 1. [Loader] target
     if let Some(target) = &migoto_config.target_exe_path {
         if !target.is_empty() {
@@ -229,8 +745,8 @@ pub async fn start_game(app: AppHandle, game_name: String) -> Result<(), String>
 
     // 4. [Hunting] analyse_options
     if let Some(auto_set) = migoto_config.auto_set_analyse_options {
-        if auto_set { 
-             ini.set("Hunting", "analyse_options", "deferred_ctx_immediate dump_rt dump_cb dump_vb dump_ib buf txt dds dump_tex dds");
+        if let (true, Some(options)) = (auto_set, &preset.analyse_options) {
+             ini.set("Hunting", "analyse_options", options);
         }
     }
 
@@ -239,12 +755,6 @@ pub async fn start_game(app: AppHandle, game_name: String) -> Result<(), String>
          ini.set("System", "dll_initialization_delay", &delay.to_string());
     }
 
-    // 6. [Hunting] hunting = 2
-    ini.set("Hunting", "hunting", "2");
-
-    // 7. [Hunting] marking_actions
-    ini.set("Hunting", "marking_actions", "clipboard asm hlsl");
-
     // 8. [Loader] delay - Maps to "autoExitSeconds" in JSON config
     if let Some(seconds) = migoto_config.auto_exit_seconds {
         ini.set("Loader", "delay", &seconds.to_string());
@@ -264,6 +774,30 @@ pub async fn start_game(app: AppHandle, game_name: String) -> Result<(), String>
     // Save changes
     ini.save()?;
 
+    Ok(())
+}
+
+// Everything after the config is loaded: d3dx.ini edits and process start-up.
+// Takes the launcher as a parameter so the sequence can run against a fake.
+async fn run_launch_sequence(
+    launcher: &dyn ProcessLauncher,
+    migoto_path: &Path,
+    migoto_config: &ThreeDMigotoConfig,
+    preset: &GamePreset,
+) -> Result<(), String> {
+    apply_d3dx_settings(migoto_path, migoto_config, preset)?;
+
+    let run_shell = migoto_config.use_shell.unwrap_or(false);
+
+    // Without Run.exe injection (Wine/Proton) the game is always started directly,
+    // with the 3Dmigoto files linked next to it
+    if !launcher.uses_run_exe() {
+        if let Some(game_dir) = wine_game_dir(migoto_config) {
+            wine_stage::stage(migoto_path, &game_dir)?;
+        }
+        return launch_target(launcher, migoto_config);
+    }
+
     // 10. Always Launch Run.exe first
     // This is the 3DMigoto Loader that handles injection.
     let run_exe_name = "Run.exe";
@@ -271,18 +805,10 @@ pub async fn start_game(app: AppHandle, game_name: String) -> Result<(), String>
     if !run_exe.exists() {
         return Err(format!("{} not found in 3DMigoto directory: {:?}", run_exe_name, migoto_path));
     }
-    
-    // Launch Run.exe using PowerShell (fixes UAC focus issues)
-    let run_script = format!(
-        "Start-Process -FilePath '{}' -WorkingDirectory '{}'", 
-        run_exe_name, 
-        migoto_path.to_string_lossy()
-    );
-    
-    Command::new("powershell")
-       .args(&["-NoProfile", "-Command", &run_script])
-       .spawn()
-       .map_err(|e| format!("Failed to launch {}: {}", run_exe_name, e))?;
+
+    let mut run_cmd = LaunchCommand::new(run_exe_name, migoto_path);
+    run_cmd.env = migoto_config.launch_env();
+    launcher.spawn(&run_cmd)?;
 
     // 11. If Shell Mode is enabled, we manually launch the game target
     // Run.exe won't do it because we removed the [Loader] launch key in INI.
@@ -291,42 +817,15 @@ pub async fn start_game(app: AppHandle, game_name: String) -> Result<(), String>
         // A short delay ensures Run.exe is watching for the process before we start it.
         println!("[GameLauncher] Waiting for Run.exe to start...");
 
-        let mut sys = System::new();
         let start_time = std::time::Instant::now();
         let timeout = std::time::Duration::from_secs(30); // 30 seconds timeout
         let mut found = false;
 
-        // Path to look for
-        let run_exe_path_str = run_exe.to_string_lossy().to_lowercase();
-
         while start_time.elapsed() < timeout {
-             // In sysinfo 0.30, refresh_processes_specific is used for detailed updates
-             // or refresh_processes for basic list.
-             // We use refresh_processes_specific to get exe path if possible
-             
-             let refresh_kind = ProcessRefreshKind::new().with_exe(UpdateKind::Always);
-             sys.refresh_processes_specifics(refresh_kind);
-
-             for (_pid, process) in sys.processes() {
-                 let proc_name = std::path::Path::new(process.name()).to_string_lossy().to_lowercase();
-                 if proc_name == "run.exe" {
-                     if let Some(exe_path) = process.exe() {
-                         if exe_path.to_string_lossy().to_lowercase() == run_exe_path_str {
-                             found = true;
-                             break;
-                         }
-                     } else {
-                         // Fallback: If we can't read the exe path (likely due to UAC),
-                         // but the name matches, we assume it's our process.
-                         found = true;
-                         break;
-                     }
-                 }
-            }
-            
-            if found {
-                 println!("[GameLauncher] Run.exe detected!");
-                 break;
+            if launcher.is_process_running(&run_exe) {
+                found = true;
+                println!("[GameLauncher] Run.exe detected!");
+                break;
             }
 
             tokio::time::sleep(std::time::Duration::from_millis(500)).await;
@@ -339,100 +838,401 @@ pub async fn start_game(app: AppHandle, game_name: String) -> Result<(), String>
              tokio::time::sleep(std::time::Duration::from_secs(1)).await;
         }
 
-        // Run Target Executable via Shell
-        let exe_path = migoto_config.launcher_exe_path
-             .or(migoto_config.target_exe_path)
-             .ok_or("No executable path found for shell launch")?;
-
-        if exe_path.is_empty() {
-             return Err("Launch path is empty".into());
-        }
-        let args = migoto_config.launch_args.unwrap_or_default();
-        
-        let path_obj = PathBuf::from(&exe_path);
-        let default_work_dir = PathBuf::from(".");
-        let work_dir = path_obj.parent().unwrap_or(&default_work_dir);
-
-        // Use Powershell Start-Process
-        let mut ps_script = format!(
-            "Start-Process -FilePath '{}' -WorkingDirectory '{}'", 
-            exe_path, 
-            work_dir.to_string_lossy()
-        );
-        
-        if !args.is_empty() {
-             ps_script.push_str(&format!(" -ArgumentList '{}'", args));
-        }
-        
-        println!("[GameLauncher] Launching target via shell: {}", exe_path);
-        Command::new("powershell")
-           .args(&["-NoProfile", "-Command", &ps_script])
-           .spawn()
-           .map_err(|e| format!("Failed to launch shell command: {}", e))?;
+        launch_target(launcher, migoto_config)?;
     }
 
     Ok(())
 }
 
-#[tauri::command]
-pub fn toggle_symlink(app: AppHandle, game_name: String, enable: bool) -> Result<(), String> {
-    let games_dir = get_global_games_dir(&app);
-    let game_dir = games_dir.join(&game_name);
-    let config_path = game_dir.join("Config.json");
+fn wine_game_dir(migoto_config: &ThreeDMigotoConfig) -> Option<PathBuf> {
+    let target = migoto_config.target_exe_path.as_deref().filter(|p| !p.trim().is_empty())?;
+    Path::new(target).parent().filter(|p| !p.as_os_str().is_empty()).map(Path::to_path_buf)
+}
+
+// Starts the launcher exe (or the game itself) without going through Run.exe
+fn launch_target(launcher: &dyn ProcessLauncher, migoto_config: &ThreeDMigotoConfig) -> Result<(), String> {
+    let exe_path = migoto_config.launcher_exe_path.clone()
+         .filter(|p| !p.is_empty())
+         .or(migoto_config.target_exe_path.clone())
+         .ok_or("No executable path found for shell launch")?;
 
-    if !config_path.exists() {
-         return Err(format!("Config file not found: {:?}", config_path));
+    if exe_path.is_empty() {
+         return Err("Launch path is empty".into());
     }
+    let args = launcher::split_args(migoto_config.launch_args.as_deref().unwrap_or_default())?;
 
-    let config_content = std::fs::read_to_string(&config_path)
-        .map_err(|e| format!("Failed to read config: {}", e))?;
-    let config_full: GameConfigFull = serde_json::from_str(&config_content)
-        .map_err(|e| format!("Failed to parse config: {}", e))?;
+    let path_obj = PathBuf::from(&exe_path);
+    let default_work_dir = PathBuf::from(".");
+    let work_dir = path_obj.parent().unwrap_or(&default_work_dir);
 
-    let migoto_config = config_full.three_d_migoto.ok_or("No 3DMigoto config found")?;
-    
-    // Resolve install_dir similar to start_game
-    let install_dir = if let Some(ref p) = migoto_config.install_dir {
-        if p.trim().is_empty() {
-             use crate::configs::app_config::AppConfig;
-             let app_config = AppConfig::load().map_err(|e| format!("Failed to load app config: {}", e))?;
-             let cache_dir = PathBuf::from(&app_config.cache_dir);
-             if app_config.cache_dir.is_empty() {
-                 return Err("3Dmigoto Path not set and Cache Dir not set.".into());
-             }
-             cache_dir.join("3Dmigoto").join(&game_name)
-        } else {
-             PathBuf::from(p)
-        }
-    } else {
-         use crate::configs::app_config::AppConfig;
-         let app_config = AppConfig::load().map_err(|e| format!("Failed to load app config: {}", e))?;
-         let cache_dir = PathBuf::from(&app_config.cache_dir);
-         if app_config.cache_dir.is_empty() {
-             return Err("3Dmigoto Path not set and Cache Dir not set.".into());
-         }
-         cache_dir.join("3Dmigoto").join(&game_name)
-    };
+    let mut cmd = LaunchCommand::new(exe_path.clone(), work_dir);
+    cmd.args = args;
+    cmd.env = migoto_config.launch_env();
+
+    println!("[GameLauncher] Launching target via {}: {}", launcher.name(), exe_path);
+    launcher.spawn(&cmd)
+}
+
+// Starts target_exe_path itself, without Run.exe or launch settings in d3dx.ini
+fn launch_vanilla(launcher: &dyn ProcessLauncher, migoto_config: &ThreeDMigotoConfig) -> Result<(), String> {
+    let exe_path = migoto_config
+        .target_exe_path
+        .clone()
+        .filter(|p| !p.trim().is_empty())
+        .ok_or("No target exe configured")?;
+
+    let path_obj = PathBuf::from(&exe_path);
+    let default_work_dir = PathBuf::from(".");
+    let mut cmd = LaunchCommand::new(exe_path.clone(), path_obj.parent().unwrap_or(&default_work_dir));
+    cmd.args = launcher::split_args(migoto_config.launch_args.as_deref().unwrap_or_default())?;
+    cmd.env = migoto_config.launch_env();
+    if !launcher.uses_run_exe() {
+        // Keep Wine from picking up 3Dmigoto's d3d11.dll next to the game
+        cmd.env.push(("WINEDLLOVERRIDES".to_string(), "d3d11=b".to_string()));
+    }
+
+    println!("[GameLauncher] Launching vanilla via {}: {}", launcher.name(), exe_path);
+    launcher.spawn(&cmd)
+}
+
+#[tauri::command]
+pub fn toggle_symlink(app: AppHandle, game_name: String, enable: bool) -> Result<(), String> {
+    let install_dir = get_game_install_dir(&app, &game_name)?;
 
     let ini_path = install_dir.join("d3dx.ini");
     if !ini_path.exists() {
         return Err(format!("d3dx.ini not found at {:?}", ini_path));
     }
 
+    let _lock = atomic_file::lock(&ini_path);
     let mut ini = IniManager::load(&ini_path)?;
-    
-    // Value definition
-    // Enable: "deferred_ctx_immediate dump_rt dump_cb dump_vb dump_ib buf txt dds dump_tex dds symlink"
-    // Disable: "deferred_ctx_immediate dump_rt dump_cb dump_vb dump_ib buf txt dds dump_tex dds"
-    
-    let val_enable = "deferred_ctx_immediate dump_rt dump_cb dump_vb dump_ib buf txt dds dump_tex dds symlink";
-    let val_disable = "deferred_ctx_immediate dump_rt dump_cb dump_vb dump_ib buf txt dds dump_tex dds";
 
-    let target_value = if enable { val_enable } else { val_disable };
+    // Base value comes from the game's preset; "symlink" is appended when enabling
+    let config = GameConfig::load_or_default(&app, &game_name)?;
+    let preset = presets::registry(&app).get_or_shared(&config.basic.game_preset);
+    let base = preset.analyse_options.unwrap_or_default();
+    let target_value = if enable { format!("{} symlink", base).trim().to_string() } else { base };
 
-    ini.set("hunting", "analyse_options", target_value);
+    ini.set("hunting", "analyse_options", &target_value);
     
     ini.save()?;
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::configs::game_config::LaunchProfile;
+    use crate::utils::launcher::RecordingLauncher;
+    use std::fs;
+
+    fn temp_migoto_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("ssmt4-launcher-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("d3dx.ini"), "[Loader]\ntarget = old.exe\nlaunch = old_launcher.exe\n").unwrap();
+        fs::write(dir.join("Run.exe"), b"").unwrap();
+        dir
+    }
+
+    fn config(use_shell: bool) -> ThreeDMigotoConfig {
+        ThreeDMigotoConfig {
+            target_exe_path: Some("C:\\Games\\Game\\Game.exe".to_string()),
+            launcher_exe_path: Some("C:\\Games\\Launcher.exe".to_string()),
+            launch_args: Some("-popupwindow".to_string()),
+            use_shell: Some(use_shell),
+            ..Default::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn run_exe_mode_only_starts_loader() {
+        let dir = temp_migoto_dir("runexe");
+        let launcher = RecordingLauncher::new(true);
+
+        run_launch_sequence(&launcher, &dir, &config(false), &GamePreset::default()).await.unwrap();
+
+        let spawned = launcher.spawned();
+        assert_eq!(spawned.len(), 1);
+        assert_eq!(spawned[0].program, "Run.exe");
+        assert_eq!(spawned[0].working_dir, dir);
+
+        let ini = IniManager::load(dir.join("d3dx.ini")).unwrap();
+        assert_eq!(ini.get("Loader", "target").as_deref(), Some("C:\\Games\\Game\\Game.exe"));
+        assert_eq!(ini.get("Loader", "launch").as_deref(), Some("C:\\Games\\Launcher.exe"));
+        assert_eq!(ini.get("Loader", "launch_args").as_deref(), Some("-popupwindow"));
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn preset_delay_is_not_written_unless_the_user_set_one() {
+        let dir = temp_migoto_dir("delay");
+        fs::write(dir.join("d3dx.ini"), "[System]\ndll_initialization_delay = 0\n").unwrap();
+        let preset: GamePreset = serde_json::from_str(
+            r#"{ "id": "WWMI", "d3dx": { "System": { "dll_initialization_delay": "500" }, "Hunting": { "hunting": "2" } } }"#,
+        )
+        .unwrap();
+        let mut cfg = config(false);
+
+        apply_d3dx_settings(&dir, &cfg, &preset).unwrap();
+        let ini = IniManager::load(dir.join("d3dx.ini")).unwrap();
+        assert_eq!(ini.get("System", "dll_initialization_delay").as_deref(), Some("0"));
+        assert_eq!(ini.get("Hunting", "hunting").as_deref(), Some("2"));
+
+        cfg.delay = Some(200);
+        apply_d3dx_settings(&dir, &cfg, &preset).unwrap();
+        let ini = IniManager::load(dir.join("d3dx.ini")).unwrap();
+        assert_eq!(ini.get("System", "dll_initialization_delay").as_deref(), Some("200"));
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[tokio::test]
+    async fn shell_mode_starts_loader_then_target() {
+        let dir = temp_migoto_dir("shell");
+        let launcher = RecordingLauncher::new(true);
+
+        run_launch_sequence(&launcher, &dir, &config(true), &GamePreset::default()).await.unwrap();
+
+        let spawned = launcher.spawned();
+        assert_eq!(spawned.len(), 2);
+        assert_eq!(spawned[0].program, "Run.exe");
+        assert_eq!(spawned[1].program, "C:\\Games\\Launcher.exe");
+        assert_eq!(spawned[1].args, vec!["-popupwindow"]);
+
+        // Run.exe must not start the game itself in shell mode
+        let ini = IniManager::load(dir.join("d3dx.ini")).unwrap();
+        assert_eq!(ini.get("Loader", "launch"), None);
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[tokio::test]
+    async fn shell_mode_splits_launch_args_into_argv() {
+        let dir = temp_migoto_dir("argv");
+        let launcher = RecordingLauncher::new(false);
+        let mut cfg = config(true);
+        cfg.launcher_exe_path = Some("D:/Games/O'Brien's Game/Game.exe".to_string());
+        cfg.launch_args = Some(r#"-popupwindow -log "D:\Game Logs\o'brien.txt" 'a "b"'"#.to_string());
+
+        run_launch_sequence(&launcher, &dir, &cfg, &GamePreset::default()).await.unwrap();
+
+        let spawned = launcher.spawned();
+        assert_eq!(spawned[0].program, "D:/Games/O'Brien's Game/Game.exe");
+        assert_eq!(spawned[0].working_dir, PathBuf::from("D:/Games/O'Brien's Game"));
+        assert_eq!(spawned[0].args, vec!["-popupwindow", "-log", r"D:\Game Logs\o'brien.txt", r#"a "b""#]);
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[tokio::test]
+    async fn unbalanced_launch_args_are_an_error() {
+        let dir = temp_migoto_dir("badargs");
+        let launcher = RecordingLauncher::new(false);
+        let mut cfg = config(true);
+        cfg.launch_args = Some("-log \"C:\\unterminated".to_string());
+
+        assert!(run_launch_sequence(&launcher, &dir, &cfg, &GamePreset::default()).await.is_err());
+        assert!(launcher.spawned().is_empty());
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn no_mods_comments_out_and_restores_mods_includes() {
+        let dir = temp_migoto_dir("nomods");
+        let d3dx = dir.join("d3dx.ini");
+        let original = "[Include]\ninclude_recursive = Mods\ninclude = ShaderFixes\\help.ini\ninclude = .\\Mods\\extra.ini\nexclude_recursive = DISABLED*\n";
+        fs::write(&d3dx, original).unwrap();
+
+        assert_eq!(disable_mods_includes(&d3dx).unwrap(), 2);
+        let ini = IniManager::load(&d3dx).unwrap();
+        assert_eq!(ini.get("Include", "include_recursive"), None);
+        assert_eq!(ini.get("Include", "include").as_deref(), Some("ShaderFixes\\help.ini"));
+
+        assert_eq!(restore_mods_includes(&d3dx).unwrap(), 2);
+        assert_eq!(fs::read_to_string(&d3dx).unwrap(), original);
+        assert_eq!(restore_mods_includes(&d3dx).unwrap(), 0);
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn relaunch_during_a_no_mods_session_keeps_mods_disabled() {
+        let dir = temp_migoto_dir("relaunch");
+        let d3dx = dir.join("d3dx.ini");
+        fs::write(&d3dx, "[Include]\ninclude_recursive = Mods\n").unwrap();
+        disable_mods_includes(&d3dx).unwrap();
+        let disabled = fs::read_to_string(&d3dx).unwrap();
+
+        // The running session re-enables the mods when the game exits
+        undo_leftover_session(&dir, true).unwrap();
+        assert_eq!(fs::read_to_string(&d3dx).unwrap(), disabled);
+
+        // Left over from a session SSMT4 never saw end
+        undo_leftover_session(&dir, false).unwrap();
+        assert_eq!(fs::read_to_string(&d3dx).unwrap(), "[Include]\ninclude_recursive = Mods\n");
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    fn empty_report() -> PreflightReport {
+        PreflightReport {
+            game_name: "Test".to_string(),
+            can_launch: true,
+            checks: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn preflight_boot_files_fail_only_when_required() {
+        let dir = temp_migoto_dir("preflight-boot");
+        let mut report = empty_report();
+
+        check_boot_file(&mut report, &dir, "d3d11.dll", "d3d11_dll", None, true);
+        check_boot_file(&mut report, &dir, "d3dcompiler_47.dll", "d3dcompiler", None, false);
+        check_boot_file(&mut report, &dir, "Run.exe", "run_exe", None, true);
+        check_boot_file(&mut report, &dir, "Run.exe", "run_exe_bundled", Some(dir.join("Run.exe")), true);
+
+        let status: Vec<(&str, CheckStatus)> = report.checks.iter().map(|c| (c.id.as_str(), c.status)).collect();
+        assert_eq!(
+            status,
+            [
+                ("d3d11_dll", CheckStatus::Fail),
+                ("d3dcompiler", CheckStatus::Warn),
+                ("run_exe", CheckStatus::Warn),
+                ("run_exe_bundled", CheckStatus::Pass),
+            ]
+        );
+        assert_eq!(report.failures().len(), 1);
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn preflight_reports_wine_game_folder_and_mod_problems() {
+        let dir = temp_migoto_dir("preflight-wine");
+        let game_dir = dir.join("Game");
+        fs::create_dir_all(&game_dir).unwrap();
+        let cfg = ThreeDMigotoConfig {
+            target_exe_path: Some(game_dir.join("Game.exe").to_string_lossy().to_string()),
+            ..Default::default()
+        };
+
+        let mut report = empty_report();
+        check_wine_game_dir(&mut report, &dir, &cfg);
+        fs::write(game_dir.join("d3d11.dll"), b"reshade").unwrap();
+        check_wine_game_dir(&mut report, &dir, &cfg);
+        assert!(report.checks.iter().all(|c| c.id == "game_d3d11" && c.status == CheckStatus::Warn));
+        assert!(report.checks[1].message.contains("its own d3d11.dll"));
+
+        fs::write(dir.join("d3dx.ini"), "[Include]\ninclude_recursive = Mods\ninclude = Missing.ini\n").unwrap();
+        fs::create_dir_all(dir.join("Mods/A")).unwrap();
+        fs::create_dir_all(dir.join("Mods/B")).unwrap();
+        fs::write(dir.join("Mods/A/a.ini"), "[TextureOverrideBody]\nhash = 1234abcd\n").unwrap();
+        fs::write(dir.join("Mods/B/b.ini"), "[TextureOverrideBody]\nhash = 1234ABCD\n").unwrap();
+        let mut report = empty_report();
+        check_mods(&mut report, &dir);
+        let ids: Vec<&str> = report.checks.iter().filter(|c| c.status == CheckStatus::Warn).map(|c| c.id.as_str()).collect();
+        assert_eq!(ids, ["includes", "mod_conflicts"]);
+        assert!(report.failures().is_empty());
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn vanilla_starts_target_without_3dmigoto() {
+        let launcher = RecordingLauncher::new(false);
+        launch_vanilla(&launcher, &config(true)).unwrap();
+
+        let spawned = launcher.spawned();
+        assert_eq!(spawned.len(), 1);
+        assert_eq!(spawned[0].program, "C:\\Games\\Game\\Game.exe");
+        assert!(spawned[0].env.contains(&("WINEDLLOVERRIDES".to_string(), "d3d11=b".to_string())));
+    }
+
+    fn config_with_profiles() -> ThreeDMigotoConfig {
+        let mut cfg = config(false);
+        cfg.env = Some(HashMap::from([("BASE".to_string(), "1".to_string()), ("MODE".to_string(), "play".to_string())]));
+        cfg.launch_profiles = Some(vec![LaunchProfile {
+            name: "Frame dump".to_string(),
+            launch_args: Some("-dx11 -window".to_string()),
+            use_shell: Some(true),
+            delay: Some(500),
+            env: Some(HashMap::from([("MODE".to_string(), "dump".to_string())])),
+            ..Default::default()
+        }]);
+        cfg.last_launch_profile = Some("Frame dump".to_string());
+        cfg
+    }
+
+    #[test]
+    fn profile_overrides_only_the_fields_it_sets() {
+        let cfg = config_with_profiles();
+
+        let (effective, name) = cfg.resolve_profile(Some("Frame dump")).unwrap();
+        assert_eq!(name.as_deref(), Some("Frame dump"));
+        assert_eq!(effective.launch_args.as_deref(), Some("-dx11 -window"));
+        assert_eq!(effective.use_shell, Some(true));
+        assert_eq!(effective.delay, Some(500));
+        assert_eq!(effective.target_exe_path, cfg.target_exe_path);
+        assert_eq!(
+            effective.launch_env(),
+            vec![("BASE".to_string(), "1".to_string()), ("MODE".to_string(), "dump".to_string())]
+        );
+
+        // No name falls back to the remembered profile, an empty name to the base config
+        assert_eq!(cfg.resolve_profile(None).unwrap().1.as_deref(), Some("Frame dump"));
+        let (base, name) = cfg.resolve_profile(Some("")).unwrap();
+        assert_eq!(name, None);
+        assert_eq!(base.launch_args.as_deref(), Some("-popupwindow"));
+
+        assert!(cfg.resolve_profile(Some("Benchmark")).is_err());
+    }
+
+    #[tokio::test]
+    async fn profile_env_reaches_spawned_processes() {
+        let dir = temp_migoto_dir("profile");
+        let launcher = RecordingLauncher::new(true);
+        let (cfg, _) = config_with_profiles().resolve_profile(Some("Frame dump")).unwrap();
+
+        run_launch_sequence(&launcher, &dir, &cfg, &GamePreset::default()).await.unwrap();
+
+        let spawned = launcher.spawned();
+        assert_eq!(spawned.len(), 2);
+        assert_eq!(spawned[1].args, vec!["-dx11", "-window"]);
+        assert!(spawned.iter().all(|c| c.env.contains(&("MODE".to_string(), "dump".to_string()))));
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[tokio::test]
+    async fn launcher_without_run_exe_starts_target_directly() {
+        let dir = temp_migoto_dir("wine");
+        fs::remove_file(dir.join("Run.exe")).unwrap();
+        fs::write(dir.join("d3d11.dll"), b"3dmigoto").unwrap();
+        let game_dir = dir.join("Game");
+        fs::create_dir_all(&game_dir).unwrap();
+        let mut cfg = config(false);
+        cfg.target_exe_path = Some(game_dir.join("Game.exe").to_string_lossy().to_string());
+        let launcher = RecordingLauncher::new(false);
+
+        run_launch_sequence(&launcher, &dir, &cfg, &GamePreset::default()).await.unwrap();
+
+        let spawned = launcher.spawned();
+        assert_eq!(spawned.len(), 1);
+        assert_eq!(spawned[0].program, "C:\\Games\\Launcher.exe");
+        // Wine looks for d3d11.dll and d3dx.ini next to the game exe
+        assert_eq!(fs::read(game_dir.join("d3d11.dll")).unwrap(), b"3dmigoto");
+        let staged_ini = IniManager::load(game_dir.join("d3dx.ini")).unwrap();
+        assert_eq!(staged_ini.get("Loader", "target"), cfg.target_exe_path);
+
+        wine_stage::unstage(&game_dir).unwrap();
+        assert!(!game_dir.join("d3d11.dll").exists());
+        assert!(!game_dir.join("d3dx.ini").exists());
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[tokio::test]
+    async fn missing_run_exe_is_an_error() {
+        let dir = temp_migoto_dir("norun");
+        fs::remove_file(dir.join("Run.exe")).unwrap();
+        let launcher = RecordingLauncher::new(true);
+
+        assert!(run_launch_sequence(&launcher, &dir, &config(false), &GamePreset::default()).await.is_err());
+        assert!(launcher.spawned().is_empty());
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,452 @@
+use crate::configs::game_config::{self, GameConfig, LaunchProfile, ThreeDMigotoConfig, CONFIG_FILE_NAME};
+use crate::configs::migrations;
+use crate::utils::file_manager::get_global_games_dir;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs::{self, File};
+use std::io::{Read, Write};
+use std::path::Path;
+use tauri::{AppHandle, Manager};
+use zip::write::FileOptions;
+use zip::ZipWriter;
+
+// A game setup package is a zip holding manifest.json, Config.json and the
+// game's Icon.png and Background.* from Games/<game>/. Nothing else is read
+// back on import, so a package cannot write outside the new game folder.
+
+const MANIFEST_FILE: &str = "manifest.json";
+const PACKAGE_FORMAT_VERSION: u32 = 1;
+const ICON_FILE: &str = "Icon.png";
+const BACKGROUND_EXTENSIONS: [&str; 7] = ["png", "webp", "jpg", "jpeg", "mp4", "webm", "mkv"];
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct PackageManifest {
+    pub format_version: u32,
+    pub game_name: String,
+    pub app_version: String,
+    pub created_at: u64,
+    pub includes_launch_profiles: bool,
+    pub files: Vec<String>,
+}
+
+// Prefix substitution applied to absolute paths on import, e.g. "D:/Games" -> "/mnt/games"
+#[derive(Debug, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct PathRemap {
+    pub from: String,
+    pub to: String,
+}
+
+#[derive(Debug, Serialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct PathChange {
+    pub field: String,            // e.g. "threeDMigoto.launchProfiles[Modding].extraDll"
+    pub original: String,
+    pub replacement: Option<String>, // None: cleared because it does not exist here
+}
+
+#[derive(Debug, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct ImportResult {
+    pub game_name: String,
+    pub renamed: bool, // A game with the package's name already existed
+    pub path_changes: Vec<PathChange>,
+}
+
+fn is_background_file(name: &str) -> bool {
+    let Some((stem, ext)) = name.rsplit_once('.') else { return false };
+    stem == "Background" && BACKGROUND_EXTENSIONS.contains(&ext.to_ascii_lowercase().as_str())
+}
+
+fn is_package_file(name: &str) -> bool {
+    name == MANIFEST_FILE || name == CONFIG_FILE_NAME || name == ICON_FILE || is_background_file(name)
+}
+
+// Windows paths are absolute to us even when importing on Linux, and vice versa
+fn looks_absolute(path: &str) -> bool {
+    let bytes = path.as_bytes();
+    Path::new(path).is_absolute()
+        || path.starts_with('/')
+        || path.starts_with("\\\\")
+        || (bytes.len() >= 3 && bytes[0].is_ascii_alphabetic() && bytes[1] == b':' && (bytes[2] == b'\\' || bytes[2] == b'/'))
+}
+
+fn normalize(path: &str) -> String {
+    path.replace('\\', "/").trim_end_matches('/').to_ascii_lowercase()
+}
+
+// None when the path should be cleared
+fn remap_path(path: &str, remaps: &[PathRemap]) -> Option<String> {
+    for remap in remaps {
+        let from = normalize(&remap.from);
+        if from.is_empty() {
+            continue;
+        }
+        let normalized = normalize(path);
+        if normalized == from || normalized.starts_with(&format!("{}/", from)) {
+            let rest = &path.replace('\\', "/")[from.len()..];
+            return Some(format!("{}{}", remap.to.trim_end_matches(['/', '\\']), rest));
+        }
+    }
+    if Path::new(path).exists() {
+        Some(path.to_string())
+    } else {
+        None
+    }
+}
+
+fn remap_field(field: &str, value: &mut Option<String>, remaps: &[PathRemap], changes: &mut Vec<PathChange>) {
+    let Some(original) = value.clone().filter(|v| looks_absolute(v.trim())) else { return };
+    let replacement = remap_path(original.trim(), remaps);
+    if replacement.as_deref() == Some(original.as_str()) {
+        return;
+    }
+    *value = replacement.clone();
+    changes.push(PathChange {
+        field: field.to_string(),
+        original,
+        replacement,
+    });
+}
+
+fn remap_profile(profile: &mut LaunchProfile, remaps: &[PathRemap], changes: &mut Vec<PathChange>) {
+    let prefix = format!("threeDMigoto.launchProfiles[{}]", profile.name);
+    remap_field(&format!("{}.targetExePath", prefix), &mut profile.target_exe_path, remaps, changes);
+    remap_field(&format!("{}.launcherExePath", prefix), &mut profile.launcher_exe_path, remaps, changes);
+    remap_field(&format!("{}.extraDll", prefix), &mut profile.extra_dll, remaps, changes);
+    remap_field(&format!("{}.wineBinary", prefix), &mut profile.wine_binary, remaps, changes);
+    remap_field(&format!("{}.winePrefix", prefix), &mut profile.wine_prefix, remaps, changes);
+}
+
+// Rewrites or clears every absolute path that does not exist on this machine.
+// A cleared installDir falls back to <cache dir>/3Dmigoto/<game>.
+pub fn remap_config_paths(config: &mut ThreeDMigotoConfig, remaps: &[PathRemap]) -> Vec<PathChange> {
+    let mut changes = Vec::new();
+    remap_field("threeDMigoto.installDir", &mut config.install_dir, remaps, &mut changes);
+    remap_field("threeDMigoto.targetExePath", &mut config.target_exe_path, remaps, &mut changes);
+    remap_field("threeDMigoto.launcherExePath", &mut config.launcher_exe_path, remaps, &mut changes);
+    remap_field("threeDMigoto.extraDll", &mut config.extra_dll, remaps, &mut changes);
+    remap_field("threeDMigoto.wineBinary", &mut config.wine_binary, remaps, &mut changes);
+    remap_field("threeDMigoto.winePrefix", &mut config.wine_prefix, remaps, &mut changes);
+    for profile in config.launch_profiles.iter_mut().flatten() {
+        remap_profile(profile, remaps, &mut changes);
+    }
+    changes
+}
+
+// Hooks run arbitrary programs, so they never travel in a package
+// Env values are often tokens or passwords (the diagnostic bundle redacts them
+// too); the names stay so whoever imports the package knows what to fill in
+fn blank_env(env: &mut Option<HashMap<String, String>>) {
+    if let Some(env) = env {
+        env.values_mut().for_each(String::clear);
+    }
+}
+
+fn strip_for_export(config: &mut GameConfig, include_launch_profiles: bool) {
+    let migoto = &mut config.three_d_migoto;
+    migoto.pre_launch_hooks = None;
+    migoto.post_exit_hooks = None;
+    blank_env(&mut migoto.env);
+    if !include_launch_profiles {
+        migoto.launch_profiles = None;
+        migoto.last_launch_profile = None;
+    }
+    for profile in migoto.launch_profiles.iter_mut().flatten() {
+        blank_env(&mut profile.env);
+    }
+}
+
+pub fn write_package(
+    game_dir: &Path,
+    game_name: &str,
+    app_version: &str,
+    include_launch_profiles: bool,
+    output: &Path,
+) -> Result<PackageManifest, String> {
+    let mut config = GameConfig::load_from(&game_dir.join(CONFIG_FILE_NAME))?;
+    strip_for_export(&mut config, include_launch_profiles);
+    let config_json =
+        serde_json::to_string_pretty(&config).map_err(|e| format!("Failed to serialize config: {}", e))?;
+
+    let mut entries: Vec<(String, Vec<u8>)> = vec![(CONFIG_FILE_NAME.to_string(), config_json.into_bytes())];
+    let mut media: Vec<String> = fs::read_dir(game_dir)
+        .map_err(|e| format!("Failed to read game folder: {}", e))?
+        .flatten()
+        .map(|e| e.file_name().to_string_lossy().to_string())
+        .filter(|name| name == ICON_FILE || is_background_file(name))
+        .collect();
+    media.sort();
+    for name in media {
+        let bytes = fs::read(game_dir.join(&name)).map_err(|e| format!("Failed to read {}: {}", name, e))?;
+        entries.push((name, bytes));
+    }
+
+    let manifest = PackageManifest {
+        format_version: PACKAGE_FORMAT_VERSION,
+        game_name: game_name.to_string(),
+        app_version: app_version.to_string(),
+        created_at: std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or(0),
+        includes_launch_profiles: include_launch_profiles && config.three_d_migoto.launch_profiles.is_some(),
+        files: entries.iter().map(|(n, _)| n.clone()).collect(),
+    };
+    let manifest_json =
+        serde_json::to_string_pretty(&manifest).map_err(|e| format!("Failed to serialize manifest: {}", e))?;
+
+    if let Some(parent) = output.parent().filter(|p| !p.as_os_str().is_empty()) {
+        fs::create_dir_all(parent).map_err(|e| format!("Failed to create output folder: {}", e))?;
+    }
+    let file = File::create(output).map_err(|e| format!("Failed to create {:?}: {}", output, e))?;
+    let mut zip = ZipWriter::new(file);
+    let options = FileOptions::default().compression_method(zip::CompressionMethod::Deflated);
+    for (name, content) in std::iter::once((MANIFEST_FILE.to_string(), manifest_json.into_bytes())).chain(entries) {
+        zip.start_file(name.as_str(), options)
+            .map_err(|e| format!("Failed to add {} to package: {}", name, e))?;
+        zip.write_all(&content)
+            .map_err(|e| format!("Failed to write {} to package: {}", name, e))?;
+    }
+    zip.finish().map_err(|e| format!("Failed to finish package: {}", e))?;
+    Ok(manifest)
+}
+
+pub struct PackageContents {
+    pub manifest: PackageManifest,
+    pub config: GameConfig,
+    pub media: Vec<(String, Vec<u8>)>,
+}
+
+pub fn read_package(archive_path: &Path) -> Result<PackageContents, String> {
+    let file = File::open(archive_path).map_err(|e| format!("Failed to open package: {}", e))?;
+    let mut archive = zip::ZipArchive::new(file).map_err(|e| format!("Not a game setup package: {}", e))?;
+
+    let mut manifest = None;
+    let mut config = None;
+    let mut media = Vec::new();
+    for i in 0..archive.len() {
+        let mut entry = archive.by_index(i).map_err(|e| format!("Failed to read package: {}", e))?;
+        let name = entry.name().to_string();
+        if !is_package_file(&name) {
+            println!("[GamePackage] Skipping unexpected entry {}", name);
+            continue;
+        }
+        let mut bytes = Vec::new();
+        entry
+            .read_to_end(&mut bytes)
+            .map_err(|e| format!("Failed to read {} from package: {}", name, e))?;
+
+        if name == MANIFEST_FILE {
+            manifest = Some(
+                serde_json::from_slice::<PackageManifest>(&bytes)
+                    .map_err(|e| format!("Failed to parse package manifest: {}", e))?,
+            );
+        } else if name == CONFIG_FILE_NAME {
+            let mut value: serde_json::Value =
+                serde_json::from_slice(&bytes).map_err(|e| format!("Failed to parse packaged config: {}", e))?;
+            migrations::migrate(&migrations::GAME_CONFIG, &mut value)?;
+            config = Some(
+                serde_json::from_value::<GameConfig>(value)
+                    .map_err(|e| format!("Failed to parse packaged config: {}", e))?,
+            );
+        } else {
+            media.push((name, bytes));
+        }
+    }
+
+    let manifest = manifest.ok_or("Not a game setup package: manifest.json is missing")?;
+    if manifest.format_version > PACKAGE_FORMAT_VERSION {
+        return Err(format!(
+            "This package was made by a newer version of SSMT4 (format {})",
+            manifest.format_version
+        ));
+    }
+    let config = config.ok_or("Game setup package has no Config.json")?;
+    Ok(PackageContents { manifest, config, media })
+}
+
+// "Name", then "Name (2)", "Name (3)", ... until one is free
+fn unique_game_name(games_dir: &Path, name: &str) -> String {
+    if !games_dir.join(name).exists() {
+        return name.to_string();
+    }
+    (2..)
+        .map(|n| format!("{} ({})", name, n))
+        .find(|candidate| !games_dir.join(candidate).exists())
+        .unwrap()
+}
+
+// Writes an imported setup into game_dir. Media from an earlier setup is
+// removed first so a stale background of the other type cannot win.
+fn install_package(game_dir: &Path, mut contents: PackageContents, remaps: &[PathRemap]) -> Result<Vec<PathChange>, String> {
+    fs::create_dir_all(game_dir).map_err(|e| format!("Failed to create game folder: {}", e))?;
+    if let Ok(entries) = fs::read_dir(game_dir) {
+        for entry in entries.flatten() {
+            let name = entry.file_name().to_string_lossy().to_string();
+            if name == ICON_FILE || is_background_file(&name) {
+                let _ = fs::remove_file(entry.path());
+            }
+        }
+    }
+
+    let migoto = &mut contents.config.three_d_migoto;
+    migoto.pre_launch_hooks = None;
+    migoto.post_exit_hooks = None;
+    let changes = remap_config_paths(migoto, remaps);
+
+    contents.config.save_to(&game_dir.join(CONFIG_FILE_NAME))?;
+    for (name, bytes) in &contents.media {
+        fs::write(game_dir.join(name), bytes).map_err(|e| format!("Failed to write {}: {}", name, e))?;
+    }
+    Ok(changes)
+}
+
+#[tauri::command]
+pub fn export_game_setup(
+    app: AppHandle,
+    game_name: String,
+    output_path: String,
+    include_launch_profiles: bool,
+) -> Result<PackageManifest, String> {
+    let game_dir = game_config::game_dir(&app, &game_name);
+    if !game_dir.join(CONFIG_FILE_NAME).exists() {
+        return Err(format!("Game not found: {}", game_name));
+    }
+    let app_version = app.package_info().version.to_string();
+    let manifest = write_package(&game_dir, &game_name, &app_version, include_launch_profiles, Path::new(&output_path))?;
+    println!("[GamePackage] Exported {} to {}", game_name, output_path);
+    Ok(manifest)
+}
+
+#[tauri::command]
+pub fn preview_game_setup(archive_path: String) -> Result<PackageManifest, String> {
+    Ok(read_package(Path::new(&archive_path))?.manifest)
+}
+
+// new_name: import under this name instead of the packaged one.
+// overwrite: replace an existing game of that name rather than picking "Name (2)".
+#[tauri::command]
+pub fn import_game_setup(
+    app: AppHandle,
+    archive_path: String,
+    new_name: Option<String>,
+    overwrite: Option<bool>,
+    path_remaps: Option<Vec<PathRemap>>,
+) -> Result<ImportResult, String> {
+    let contents = read_package(Path::new(&archive_path))?;
+    let requested = new_name
+        .map(|n| n.trim().to_string())
+        .filter(|n| !n.is_empty())
+        .unwrap_or_else(|| contents.manifest.game_name.clone());
+    game_config::validate_game_name(&requested)?;
+
+    let games_dir = get_global_games_dir(&app);
+    let game_name = if overwrite.unwrap_or(false) {
+        requested.clone()
+    } else {
+        unique_game_name(&games_dir, &requested)
+    };
+    let game_dir = games_dir.join(&game_name);
+
+    let path_changes = install_package(&game_dir, contents, &path_remaps.unwrap_or_default())?;
+    println!(
+        "[GamePackage] Imported {} as {} ({} path(s) changed)",
+        archive_path,
+        game_name,
+        path_changes.len()
+    );
+    Ok(ImportResult {
+        renamed: game_name != requested,
+        game_name,
+        path_changes,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn missing_paths_are_remapped_or_cleared() {
+        let here = std::env::temp_dir().to_string_lossy().to_string();
+        let mut config = ThreeDMigotoConfig {
+            install_dir: Some("D:\\3Dmigoto\\GIMI".to_string()),
+            target_exe_path: Some("D:\\Games\\Genshin Impact\\GenshinImpact.exe".to_string()),
+            extra_dll: Some(here.clone()),
+            launch_args: Some("-popupwindow".to_string()),
+            launch_profiles: Some(vec![LaunchProfile {
+                name: "Modding".to_string(),
+                launcher_exe_path: Some("D:/Games/Launcher.exe".to_string()),
+                ..Default::default()
+            }]),
+            ..Default::default()
+        };
+        let remaps = [PathRemap {
+            from: "d:/games/".to_string(),
+            to: "/mnt/games".to_string(),
+        }];
+
+        let changes = remap_config_paths(&mut config, &remaps);
+        assert_eq!(config.install_dir, None);
+        assert_eq!(config.target_exe_path.as_deref(), Some("/mnt/games/Genshin Impact/GenshinImpact.exe"));
+        assert_eq!(config.extra_dll.as_deref(), Some(here.as_str()));
+        assert_eq!(config.launch_args.as_deref(), Some("-popupwindow"));
+        assert_eq!(
+            config.launch_profiles.as_ref().unwrap()[0].launcher_exe_path.as_deref(),
+            Some("/mnt/games/Launcher.exe")
+        );
+        assert_eq!(changes.len(), 3);
+        assert_eq!(changes[0].field, "threeDMigoto.installDir");
+        assert_eq!(changes[0].replacement, None);
+        assert_eq!(changes[2].field, "threeDMigoto.launchProfiles[Modding].launcherExePath");
+    }
+
+    #[test]
+    fn package_round_trip_without_profiles_or_hooks() {
+        let root = std::env::temp_dir().join(format!("ssmt4-game-package-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+        let source = root.join("Games").join("Genshin");
+        fs::create_dir_all(&source).unwrap();
+        fs::write(
+            source.join(CONFIG_FILE_NAME),
+            r#"{ "basic": { "gamePreset": "GIMI" }, "threeDMigoto": {
+                "delay": 100,
+                "env": { "API_TOKEN": "hunter2-base" },
+                "launchProfiles": [{ "name": "Modding", "env": { "PASSWORD": "hunter2-profile" } }],
+                "preLaunchHooks": [{ "name": "x", "command": "calc.exe" }] } }"#,
+        )
+        .unwrap();
+        fs::write(source.join(ICON_FILE), b"icon").unwrap();
+        fs::write(source.join("Background.webm"), b"video").unwrap();
+        fs::write(source.join("PlayHistory.json"), b"{}").unwrap();
+
+        let archive = root.join("Genshin.zip");
+        let manifest = write_package(&source, "Genshin", "1.0.0", false, &archive).unwrap();
+        assert!(!manifest.includes_launch_profiles);
+        assert_eq!(manifest.files, vec![CONFIG_FILE_NAME, "Background.webm", ICON_FILE]);
+
+        let contents = read_package(&archive).unwrap();
+        assert_eq!(contents.manifest.game_name, "Genshin");
+        assert!(contents.config.three_d_migoto.launch_profiles.is_none());
+        assert!(contents.config.three_d_migoto.pre_launch_hooks.is_none());
+        assert_eq!(contents.config.three_d_migoto.delay, Some(100));
+        assert_eq!(contents.config.three_d_migoto.env.as_ref().unwrap()["API_TOKEN"], "");
+
+        let with_profiles = root.join("Genshin-profiles.zip");
+        write_package(&source, "Genshin", "1.0.0", true, &with_profiles).unwrap();
+        let config = read_package(&with_profiles).unwrap().config;
+        let exported = serde_json::to_string(&config).unwrap();
+        assert!(exported.contains("PASSWORD"));
+        assert!(!exported.contains("hunter2"));
+
+        let games_dir = root.join("Games");
+        let name = unique_game_name(&games_dir, "Genshin");
+        assert_eq!(name, "Genshin (2)");
+        install_package(&games_dir.join(&name), contents, &[]).unwrap();
+        assert_eq!(fs::read(games_dir.join(&name).join("Background.webm")).unwrap(), b"video");
+        assert!(!games_dir.join(&name).join("PlayHistory.json").exists());
+        let _ = fs::remove_dir_all(&root);
+    }
+}
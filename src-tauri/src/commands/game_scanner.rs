@@ -1,7 +1,9 @@
-﻿use crate::utils::file_manager::get_global_games_dir;
+﻿use crate::configs::game_config::{GameConfig, CONFIG_FILE_NAME};
+use crate::configs::recovery;
+use crate::utils::atomic_file;
+use crate::utils::file_manager::get_global_games_dir;
 use serde::{Deserialize, Serialize};
 use std::{
-    collections::HashMap,
     fs,
     path::{Path, PathBuf},
 };
@@ -22,34 +24,93 @@ pub struct GameInfo {
     pub bg_video_path: Option<PathBuf>,
     pub bg_type: BGType, // "image" or "video"
     pub show_sidebar: bool,
+    pub favorite: bool,
+    pub categories: Vec<String>,
+    pub last_played_at: Option<u64>, // Unix seconds
+    pub order: Option<u32>, // None until the user reorders the sidebar
 }
 
-#[derive(Deserialize)]
-#[serde(rename_all = "camelCase")]
-struct PartialGameConfig {
-    #[serde(default)]
-    basic: PartialBasicSettings,
+// Everything past GameName/Show is optional so files from older releases load as is
+#[derive(Serialize, Deserialize, Clone, Default)]
+pub(crate) struct GameIconSetting {
+    #[serde(rename = "GameName")]
+    pub game_name: String,
+    #[serde(rename = "Show")]
+    pub show: bool,
+    // Sidebar position; games without one follow the ordered ones, by name
+    #[serde(rename = "Order", default, skip_serializing_if = "Option::is_none")]
+    pub order: Option<u32>,
+    #[serde(rename = "Favorite", default)]
+    pub favorite: bool,
+    #[serde(rename = "Categories", default, skip_serializing_if = "Vec::is_empty")]
+    pub categories: Vec<String>,
+    #[serde(rename = "LastPlayedAt", default, skip_serializing_if = "Option::is_none")]
+    pub last_played_at: Option<u64>,
 }
 
-#[derive(Deserialize, Default)]
-#[serde(rename_all = "camelCase")]
-struct PartialBasicSettings {
-    #[serde(default)]
-    background_type: Option<BGType>,
+#[derive(Serialize, Deserialize, Default)]
+pub(crate) struct GameIconConfig {
+    #[serde(rename = "GameIconSettingList")]
+    pub list: Vec<GameIconSetting>,
+    // User categories in display order, including ones no game uses yet
+    #[serde(rename = "Categories", default, skip_serializing_if = "Vec::is_empty")]
+    pub categories: Vec<String>,
 }
 
-#[derive(Serialize, Deserialize)]
-struct GameIconSetting {
-    #[serde(rename = "GameName")]
-    game_name: String,
-    #[serde(rename = "Show")]
-    show: bool,
+impl GameIconConfig {
+    pub fn get(&self, game_name: &str) -> Option<&GameIconSetting> {
+        self.list.iter().find(|x| x.game_name == game_name)
+    }
+
+    // Adds a hidden entry for games that have none yet
+    pub fn entry_mut(&mut self, game_name: &str) -> &mut GameIconSetting {
+        let index = match self.list.iter().position(|x| x.game_name == game_name) {
+            Some(index) => index,
+            None => {
+                self.list.push(GameIconSetting {
+                    game_name: game_name.to_string(),
+                    ..Default::default()
+                });
+                self.list.len() - 1
+            }
+        };
+        &mut self.list[index]
+    }
 }
 
-#[derive(Serialize, Deserialize)]
-struct GameIconConfig {
-    #[serde(rename = "GameIconSettingList")]
-    list: Vec<GameIconSetting>,
+const ICON_CONFIG_FILE: &str = "GameIconConfig.json";
+
+// Empty config if the file does not exist yet
+pub(crate) fn load_icon_config(games_dir: &Path) -> Result<GameIconConfig, String> {
+    let config_path = games_dir.join(ICON_CONFIG_FILE);
+    if !config_path.exists() {
+        return Ok(GameIconConfig::default());
+    }
+    let content = recovery::read_lossy(&config_path)?;
+    match recovery::find_damage::<GameIconConfig>(&content, None)? {
+        Some(e) => recovery::recover(&config_path, &content, &e, None),
+        None => serde_json::from_str(content.trim_start_matches('\u{feff}'))
+            .map_err(|e| format!("Failed to parse config file: {}", e)),
+    }
+}
+
+pub(crate) fn save_icon_config(games_dir: &Path, config: &GameIconConfig) -> Result<(), String> {
+    let new_content = serde_json::to_string_pretty(config)
+        .map_err(|e| format!("Failed to serialize config: {}", e))?;
+    atomic_file::write(&games_dir.join(ICON_CONFIG_FILE), new_content)
+        .map_err(|e| format!("Failed to write config file: {}", e))
+}
+
+// Load, change and save GameIconConfig.json while holding its lock
+pub(crate) fn update_icon_config<T>(
+    games_dir: &Path,
+    change: impl FnOnce(&mut GameIconConfig) -> T,
+) -> Result<T, String> {
+    let _lock = atomic_file::lock(&games_dir.join(ICON_CONFIG_FILE));
+    let mut config = load_icon_config(games_dir)?;
+    let result = change(&mut config);
+    save_icon_config(games_dir, &config)?;
+    Ok(result)
 }
 
 // Function to find games directory (shared logic)
@@ -76,19 +137,10 @@ pub fn scan_games(app: AppHandle) -> Result<Vec<GameInfo>, String> {
     println!("Scanning games in: {}", normalize_path(&games_dir));
 
     // Load GameIconConfig.json if it exists
-    let mut sidebar_config: HashMap<String, bool> = HashMap::new();
-    let config_path = games_dir.join("GameIconConfig.json");
-    if config_path.exists() {
-        if let Ok(content) = fs::read_to_string(&config_path) {
-            if let Ok(parsed) = serde_json::from_str::<GameIconConfig>(&content) {
-                for item in parsed.list {
-                    sidebar_config.insert(item.game_name, item.show);
-                }
-            } else {
-                eprintln!("Failed to parse GameIconConfig.json");
-            }
-        }
-    }
+    let icon_config = load_icon_config(&games_dir).unwrap_or_else(|e| {
+        eprintln!("{}", e);
+        GameIconConfig::default()
+    });
 
     let mut games = Vec::new();
     let entries =
@@ -120,15 +172,10 @@ pub fn scan_games(app: AppHandle) -> Result<Vec<GameInfo>, String> {
 
                     // Determine background type from Config.json
                     let mut bg_type = BGType::Image;
-                    let config_path = path.join("Config.json");
+                    let config_path = path.join(CONFIG_FILE_NAME);
                     if config_path.exists() {
-                        if let Ok(content) = fs::read_to_string(&config_path) {
-                            if let Ok(config) = serde_json::from_str::<PartialGameConfig>(&content)
-                            {
-                                if let Some(t) = config.basic.background_type {
-                                    bg_type = t;
-                                }
-                            }
+                        if let Ok(config) = GameConfig::load_from(&config_path) {
+                            bg_type = config.basic.background_type;
                         }
                     }
 
@@ -141,8 +188,8 @@ pub fn scan_games(app: AppHandle) -> Result<Vec<GameInfo>, String> {
                     }
                     // Background warnings might be spammy if only video exists, but keeps consistent
 
-                    // Determine show_sidebar status
-                    let show_sidebar = *sidebar_config.get(name).unwrap_or(&false);
+                    // Sidebar state, hidden unless the user added it
+                    let setting = icon_config.get(name).cloned().unwrap_or_default();
 
                     games.push(GameInfo {
                         name: name.to_string(),
@@ -150,46 +197,145 @@ pub fn scan_games(app: AppHandle) -> Result<Vec<GameInfo>, String> {
                         bg_path,
                         bg_video_path: video_path,
                         bg_type,
-                        show_sidebar,
+                        show_sidebar: setting.show,
+                        favorite: setting.favorite,
+                        categories: setting.categories,
+                        last_played_at: setting.last_played_at,
+                        order: setting.order,
                     });
                 }
             }
         }
     }
 
+    // read_dir order differs between filesystems
+    sort_games(&mut games, &icon_config);
     Ok(games)
 }
 
+// Explicit Order first, then by name
+fn sort_games(games: &mut [GameInfo], icon_config: &GameIconConfig) {
+    games.sort_by_cached_key(|g| {
+        let order = icon_config.get(&g.name).and_then(|s| s.order).unwrap_or(u32::MAX);
+        (order, g.name.to_lowercase())
+    });
+}
+
 #[tauri::command]
 pub fn set_game_visibility(app: AppHandle, game_name: String, visible: bool) -> Result<(), String> {
     let games_dir = find_games_dir(&app);
-    let config_path = games_dir.join("GameIconConfig.json");
-
-    // Read existing config or create new
-    let mut config = if config_path.exists() {
-        let content = fs::read_to_string(&config_path)
-            .map_err(|e| format!("Failed to read config file: {}", e))?;
-        serde_json::from_str::<GameIconConfig>(&content)
-            .map_err(|e| format!("Failed to parse config file: {}", e))?
-    } else {
-        GameIconConfig { list: Vec::new() }
-    };
-
-    // Update or Add entry
-    if let Some(entry) = config.list.iter_mut().find(|x| x.game_name == game_name) {
-        entry.show = visible;
-    } else {
-        config.list.push(GameIconSetting {
-            game_name,
-            show: visible,
-        });
+    update_icon_config(&games_dir, |config| config.entry_mut(&game_name).show = visible)
+}
+
+#[tauri::command]
+pub fn set_game_favorite(app: AppHandle, game_name: String, favorite: bool) -> Result<(), String> {
+    let games_dir = find_games_dir(&app);
+    update_icon_config(&games_dir, |config| config.entry_mut(&game_name).favorite = favorite)
+}
+
+// order: game names top to bottom. It may list only some games (e.g. just the
+// sidebar ones); other ordered games keep their relative order after them.
+#[tauri::command]
+pub fn reorder_games(app: AppHandle, order: Vec<String>) -> Result<(), String> {
+    let games_dir = find_games_dir(&app);
+    update_icon_config(&games_dir, |config| {
+        let mut rest: Vec<(u32, String)> = config
+            .list
+            .iter()
+            .filter(|s| !order.contains(&s.game_name))
+            .filter_map(|s| s.order.map(|o| (o, s.game_name.clone())))
+            .collect();
+        rest.sort();
+
+        let names = order.iter().cloned().chain(rest.into_iter().map(|(_, name)| name));
+        for (index, name) in names.enumerate() {
+            config.entry_mut(&name).order = Some(index as u32);
+        }
+    })
+}
+
+fn clean_categories(categories: Vec<String>) -> Vec<String> {
+    let mut cleaned: Vec<String> = Vec::new();
+    for category in categories {
+        let category = category.trim().to_string();
+        if !category.is_empty() && !cleaned.contains(&category) {
+            cleaned.push(category);
+        }
     }
+    cleaned
+}
 
-    // Write back
-    let new_content = serde_json::to_string_pretty(&config)
-        .map_err(|e| format!("Failed to serialize config: {}", e))?;
-    fs::write(&config_path, new_content)
-        .map_err(|e| format!("Failed to write config file: {}", e))?;
+// Replaces the game's categories; new names are added to the category list
+#[tauri::command]
+pub fn set_game_categories(app: AppHandle, game_name: String, categories: Vec<String>) -> Result<(), String> {
+    let categories = clean_categories(categories);
+    let games_dir = find_games_dir(&app);
+    update_icon_config(&games_dir, |config| {
+        for category in &categories {
+            if !config.categories.contains(category) {
+                config.categories.push(category.clone());
+            }
+        }
+        config.entry_mut(&game_name).categories = categories;
+    })
+}
 
-    Ok(())
+#[tauri::command]
+pub fn get_game_categories(app: AppHandle) -> Result<Vec<String>, String> {
+    Ok(load_icon_config(&find_games_dir(&app))?.categories)
+}
+
+// Sets the category list and its order. Categories left out are deleted and
+// taken off every game.
+#[tauri::command]
+pub fn set_game_category_list(app: AppHandle, categories: Vec<String>) -> Result<(), String> {
+    let categories = clean_categories(categories);
+    let games_dir = find_games_dir(&app);
+    update_icon_config(&games_dir, |config| {
+        for setting in config.list.iter_mut() {
+            setting.categories.retain(|c| categories.contains(c));
+        }
+        config.categories = categories;
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn game(name: &str) -> GameInfo {
+        GameInfo {
+            name: name.to_string(),
+            icon_path: PathBuf::new(),
+            bg_path: PathBuf::new(),
+            bg_video_path: None,
+            bg_type: BGType::Image,
+            show_sidebar: false,
+            favorite: false,
+            categories: Vec::new(),
+            last_played_at: None,
+            order: None,
+        }
+    }
+
+    #[test]
+    fn old_icon_config_loads_and_games_sort_by_order_then_name() {
+        let old = r#"{ "GameIconSettingList": [
+            { "GameName": "SRMI", "Show": true },
+            { "GameName": "ZZMI", "Show": false }
+        ] }"#;
+        let mut config: GameIconConfig = serde_json::from_str(old).unwrap();
+        assert!(config.get("SRMI").is_some_and(|s| s.show && s.order.is_none() && !s.favorite));
+
+        config.entry_mut("ZZMI").order = Some(0);
+        config.entry_mut("himi").order = Some(1);
+        let mut games: Vec<GameInfo> = ["SRMI", "GIMI", "himi", "ZZMI", "aemi"].into_iter().map(game).collect();
+        sort_games(&mut games, &config);
+        let names: Vec<&str> = games.iter().map(|g| g.name.as_str()).collect();
+        assert_eq!(names, ["ZZMI", "himi", "aemi", "GIMI", "SRMI"]);
+
+        // Optional fields stay out of the file until used
+        let saved = serde_json::to_string(&config).unwrap();
+        assert!(!saved.contains("Categories") && !saved.contains("LastPlayedAt"));
+    }
 }
@@ -0,0 +1,380 @@
+use crate::commands::game_scanner::update_icon_config;
+use crate::utils::atomic_file;
+use crate::utils::file_manager::get_global_games_dir;
+use crate::utils::hooks::{self, HookCommand};
+use crate::utils::launch_backup;
+use crate::utils::launcher::{find_process_pid, is_pid_running};
+use crate::utils::wine_stage;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::time::Duration;
+use tauri::{AppHandle, Emitter, Manager, State};
+
+const HISTORY_FILE: &str = "PlayHistory.json";
+pub const HOOK_LOG_FILE: &str = "HookLog.txt";
+const MAX_HISTORY_ENTRIES: usize = 200;
+
+// How long to wait for the game process after start_game returns. Launchers
+// such as HoYoPlay may sit in front of the game for a while.
+const START_TIMEOUT: Duration = Duration::from_secs(600);
+const POLL_INTERVAL: Duration = Duration::from_secs(2);
+
+// Games currently being watched, keyed by game name
+pub struct GameSessions(pub Mutex<HashMap<String, ActiveSession>>);
+
+#[derive(Debug, Default)]
+pub struct ActiveSession {
+    pub pid: Option<u32>, // None while still waiting for the process to appear
+    pub exit_actions: ExitActions,
+}
+
+// Work to do once the monitored game process has exited
+#[derive(Debug, Clone, Default)]
+pub struct ExitActions {
+    pub post_exit_hooks: Vec<HookCommand>,
+    pub restore_mods_include: Option<PathBuf>, // d3dx.ini of a "no mods" run
+    pub restore_backup: Option<PathBuf>,       // 3Dmigoto folder with a launch backup to put back
+    pub unstage_wine: Option<PathBuf>,         // Game folder the 3Dmigoto files were linked into for Wine
+}
+
+impl ExitActions {
+    // A second launch while the game is still watched hands its actions to the running session
+    fn merge(&mut self, other: ExitActions) {
+        for hook in other.post_exit_hooks {
+            if !self.post_exit_hooks.contains(&hook) {
+                self.post_exit_hooks.push(hook);
+            }
+        }
+        self.restore_mods_include = self.restore_mods_include.take().or(other.restore_mods_include);
+        self.restore_backup = self.restore_backup.take().or(other.restore_backup);
+        self.unstage_wine = self.unstage_wine.take().or(other.unstage_wine);
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct SessionRecord {
+    pub pid: u32,
+    pub started_at: u64,
+    pub exited_at: Option<u64>, // None if SSMT4 was closed while the game ran
+    pub duration_secs: u64,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct PlayHistory {
+    pub total_playtime_secs: u64,
+    pub launch_count: u64,
+    pub last_played_at: Option<u64>,
+    pub sessions: Vec<SessionRecord>,
+}
+
+#[derive(Debug, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct GameStartedEvent {
+    pub game_name: String,
+    pub pid: u32,
+    pub started_at: u64,
+}
+
+#[derive(Debug, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct GameExitedEvent {
+    pub game_name: String,
+    pub pid: u32,
+    pub started_at: u64,
+    pub exited_at: u64,
+    pub duration_secs: u64,
+}
+
+fn now_secs() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+fn history_path(app: &AppHandle, game_name: &str) -> PathBuf {
+    get_global_games_dir(app).join(game_name).join(HISTORY_FILE)
+}
+
+fn record_start(history: &mut PlayHistory, pid: u32, started_at: u64) {
+    history.launch_count += 1;
+    history.last_played_at = Some(started_at);
+    history.sessions.push(SessionRecord {
+        pid,
+        started_at,
+        exited_at: None,
+        duration_secs: 0,
+    });
+    if history.sessions.len() > MAX_HISTORY_ENTRIES {
+        let overflow = history.sessions.len() - MAX_HISTORY_ENTRIES;
+        history.sessions.drain(..overflow);
+    }
+}
+
+fn record_exit(history: &mut PlayHistory, pid: u32, started_at: u64, exited_at: u64) {
+    let duration_secs = exited_at.saturating_sub(started_at);
+    if let Some(record) = history
+        .sessions
+        .iter_mut()
+        .rev()
+        .find(|r| r.pid == pid && r.started_at == started_at)
+    {
+        record.exited_at = Some(exited_at);
+        record.duration_secs = duration_secs;
+    }
+    history.total_playtime_secs += duration_secs;
+}
+
+fn load_history(app: &AppHandle, game_name: &str) -> PlayHistory {
+    fs::read_to_string(history_path(app, game_name))
+        .ok()
+        .and_then(|content| serde_json::from_str(&content).ok())
+        .unwrap_or_default()
+}
+
+fn save_history(app: &AppHandle, game_name: &str, history: &PlayHistory) -> Result<(), String> {
+    let content = serde_json::to_string_pretty(history)
+        .map_err(|e| format!("Failed to serialize play history: {}", e))?;
+    atomic_file::write(&history_path(app, game_name), content)
+        .map_err(|e| format!("Failed to write play history: {}", e))
+}
+
+pub fn is_monitored(app: &AppHandle, game_name: &str) -> bool {
+    app.try_state::<GameSessions>()
+        .is_some_and(|sessions| sessions.0.lock().unwrap().contains_key(game_name))
+}
+
+// Returns true if game_name was not watched yet and needs a monitor. Otherwise
+// the running session takes over the exit actions.
+fn register_session(active: &mut HashMap<String, ActiveSession>, game_name: &str, exit_actions: ExitActions) -> bool {
+    match active.get_mut(game_name) {
+        Some(session) => {
+            session.exit_actions.merge(exit_actions);
+            false
+        }
+        None => {
+            active.insert(
+                game_name.to_string(),
+                ActiveSession {
+                    pid: None,
+                    exit_actions,
+                },
+            );
+            true
+        }
+    }
+}
+
+// Ends the session and hands back everything queued for its exit. Done under
+// one lock so a launch merging actions in cannot slip in between.
+fn finish_session(active: &mut HashMap<String, ActiveSession>, game_name: &str) -> ExitActions {
+    active.remove(game_name).map(|s| s.exit_actions).unwrap_or_default()
+}
+
+// Polls for target_exe after a launch and records the session once it exits.
+// If this game is already being watched, its exit actions join that session.
+pub fn start_session_monitor(app: AppHandle, game_name: String, target_exe: PathBuf, exit_actions: ExitActions) {
+    let is_new = register_session(&mut app.state::<GameSessions>().0.lock().unwrap(), &game_name, exit_actions);
+    if !is_new {
+        println!("[GameSession] {} is already being monitored, its exit actions were merged", game_name);
+        return;
+    }
+
+    tauri::async_runtime::spawn(async move {
+        let played = monitor_session(&app, &game_name, &target_exe).await;
+        let exit_actions = finish_session(&mut app.state::<GameSessions>().0.lock().unwrap(), &game_name);
+        run_exit_actions(&app, &game_name, &exit_actions, played).await;
+    });
+}
+
+// Returns true once a detected game process has exited, false if it never showed up
+async fn monitor_session(app: &AppHandle, game_name: &str, target_exe: &std::path::Path) -> bool {
+    println!("[GameSession] Waiting for {:?}", target_exe);
+
+    let wait_start = std::time::Instant::now();
+    let pid = loop {
+        if let Some(pid) = find_process_pid(target_exe) {
+            break pid;
+        }
+        if wait_start.elapsed() > START_TIMEOUT {
+            println!("[GameSession] {} did not start within {:?}", game_name, START_TIMEOUT);
+            return false;
+        }
+        tokio::time::sleep(POLL_INTERVAL).await;
+    };
+
+    let started_at = now_secs();
+    if let Some(session) = app.state::<GameSessions>().0.lock().unwrap().get_mut(game_name) {
+        session.pid = Some(pid);
+    }
+
+    let history_lock = atomic_file::lock(&history_path(app, game_name));
+    let mut history = load_history(app, game_name);
+    record_start(&mut history, pid, started_at);
+    if let Err(e) = save_history(app, game_name, &history) {
+        eprintln!("[GameSession] {}", e);
+    }
+    drop(history_lock);
+    // Also kept in GameIconConfig.json so scan_games can report it without reading every history file
+    let icon_update = update_icon_config(&get_global_games_dir(app), |config| {
+        config.entry_mut(game_name).last_played_at = Some(started_at);
+    });
+    if let Err(e) = icon_update {
+        eprintln!("[GameSession] {}", e);
+    }
+
+    println!("[GameSession] {} started with PID {}", game_name, pid);
+    let _ = app.emit(
+        "game-started",
+        GameStartedEvent {
+            game_name: game_name.to_string(),
+            pid,
+            started_at,
+        },
+    );
+
+    while is_pid_running(pid) {
+        tokio::time::sleep(POLL_INTERVAL).await;
+    }
+
+    let exited_at = now_secs();
+    let duration_secs = exited_at.saturating_sub(started_at);
+
+    // Re-read in case something else touched the file during the session
+    let history_lock = atomic_file::lock(&history_path(app, game_name));
+    let mut history = load_history(app, game_name);
+    record_exit(&mut history, pid, started_at, exited_at);
+    if let Err(e) = save_history(app, game_name, &history) {
+        eprintln!("[GameSession] {}", e);
+    }
+    drop(history_lock);
+
+    println!("[GameSession] {} exited after {}s", game_name, duration_secs);
+    let _ = app.emit(
+        "game-exited",
+        GameExitedEvent {
+            game_name: game_name.to_string(),
+            pid,
+            started_at,
+            exited_at,
+            duration_secs,
+        },
+    );
+    true
+}
+
+// Restores run even if the game never showed up, hooks only after a real session
+async fn run_exit_actions(app: &AppHandle, game_name: &str, actions: &ExitActions, played: bool) {
+    if let Some(migoto_path) = &actions.restore_backup {
+        // The game may hold d3d11.dll for a moment after its process is gone
+        let mut attempts = 0;
+        while let Err(e) = launch_backup::restore_backup(migoto_path) {
+            attempts += 1;
+            if attempts >= 5 {
+                eprintln!("[GameSession] {}. It will be retried on the next launch.", e);
+                break;
+            }
+            tokio::time::sleep(POLL_INTERVAL).await;
+        }
+    }
+    if let Some(game_dir) = &actions.unstage_wine {
+        if let Err(e) = wine_stage::unstage(game_dir) {
+            eprintln!("[GameSession] {}. It will be retried on the next launch.", e);
+        }
+    }
+    if let Some(d3dx_path) = &actions.restore_mods_include {
+        if let Err(e) = crate::commands::game_launcher::restore_mods_includes(d3dx_path) {
+            eprintln!("[GameSession] Failed to re-enable mods: {}", e);
+        }
+    }
+
+    if played && !actions.post_exit_hooks.is_empty() {
+        let log_path = get_global_games_dir(app).join(game_name).join(HOOK_LOG_FILE);
+        // abort_on_failure has nothing left to abort after exit
+        if let Err(e) = hooks::run_hooks(&actions.post_exit_hooks, "post-exit", &log_path).await {
+            eprintln!("[GameSession] {}", e);
+        }
+    }
+}
+
+#[tauri::command]
+pub fn get_play_history(app: AppHandle, game_name: String) -> Result<PlayHistory, String> {
+    Ok(load_history(&app, &game_name))
+}
+
+// Game name -> PID (None while still waiting for the process to appear)
+#[tauri::command]
+pub fn get_running_games(state: State<'_, GameSessions>) -> Result<HashMap<String, Option<u32>>, String> {
+    Ok(state.0.lock().unwrap().iter().map(|(name, session)| (name.clone(), session.pid)).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hook(name: &str) -> HookCommand {
+        HookCommand {
+            name: name.to_string(),
+            command: "cmd".to_string(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn relaunch_during_a_session_merges_exit_actions() {
+        let mut active = HashMap::new();
+        let first = ExitActions {
+            post_exit_hooks: vec![hook("stop overlay")],
+            restore_backup: Some(PathBuf::from("/3dm")),
+            ..Default::default()
+        };
+        assert!(register_session(&mut active, "SRMI", first));
+
+        // A "no mods" relaunch while the game still runs
+        let second = ExitActions {
+            post_exit_hooks: vec![hook("stop overlay"), hook("sync saves")],
+            restore_mods_include: Some(PathBuf::from("/3dm/d3dx.ini")),
+            restore_backup: Some(PathBuf::from("/3dm")),
+            ..Default::default()
+        };
+        assert!(!register_session(&mut active, "SRMI", second));
+        assert!(register_session(&mut active, "GIMI", ExitActions::default()));
+
+        let actions = finish_session(&mut active, "SRMI");
+        assert_eq!(actions.post_exit_hooks, vec![hook("stop overlay"), hook("sync saves")]);
+        assert_eq!(actions.restore_mods_include, Some(PathBuf::from("/3dm/d3dx.ini")));
+        assert_eq!(actions.restore_backup, Some(PathBuf::from("/3dm")));
+        assert!(!active.contains_key("SRMI"));
+        assert!(active.contains_key("GIMI"));
+        assert!(finish_session(&mut active, "SRMI").post_exit_hooks.is_empty());
+    }
+
+    #[test]
+    fn history_records_sessions_and_playtime() {
+        let mut history = PlayHistory::default();
+        record_start(&mut history, 100, 1_000);
+        record_start(&mut history, 200, 5_000);
+        record_exit(&mut history, 100, 1_000, 1_600);
+        // An exit for a record that was already trimmed still counts the playtime
+        record_exit(&mut history, 999, 10, 70);
+
+        assert_eq!(history.launch_count, 2);
+        assert_eq!(history.last_played_at, Some(5_000));
+        assert_eq!(history.sessions[0].exited_at, Some(1_600));
+        assert_eq!(history.sessions[0].duration_secs, 600);
+        assert_eq!(history.sessions[1].exited_at, None);
+        assert_eq!(history.total_playtime_secs, 660);
+
+        for i in 0..MAX_HISTORY_ENTRIES as u32 {
+            record_start(&mut history, i, 10_000 + i as u64);
+        }
+        assert_eq!(history.sessions.len(), MAX_HISTORY_ENTRIES);
+        assert_eq!(history.sessions[0].pid, 0);
+        assert_eq!(history.launch_count, 2 + MAX_HISTORY_ENTRIES as u64);
+    }
+}
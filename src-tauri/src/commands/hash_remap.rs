@@ -0,0 +1,420 @@
+use crate::configs::game_config::get_game_install_dir;
+use crate::utils::file_manager::join_inside;
+use crate::utils::ini_manager::IniManager;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use tauri::AppHandle;
+
+// Backups live outside Mods so 3Dmigoto never loads the old ini copies
+const BACKUP_DIR: &str = "SSMT4Backups/HashRemap";
+const MANIFEST_NAME: &str = "manifest.json";
+
+#[derive(Debug, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct HashRemapChange {
+    pub file: String, // Relative to Mods
+    pub line: usize,
+    pub old_line: String,
+    pub new_line: String,
+}
+
+#[derive(Debug, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct HashRemapSkipped {
+    pub file: String, // Relative to Mods
+    pub reason: String,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HashRemapResult {
+    pub changes: Vec<HashRemapChange>,
+    pub files_changed: usize,
+    pub dry_run: bool,
+    pub backup_id: Option<String>,
+    pub skipped: Vec<HashRemapSkipped>, // Files with hashes to remap that cannot be rewritten
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct HashRemapBackup {
+    pub id: String,
+    pub created_at: u64,
+    pub target: String,
+    pub files: Vec<String>, // Relative to Mods
+}
+
+#[derive(Deserialize)]
+struct HashPair {
+    old: String,
+    new: String,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum HashTableJson {
+    Map(HashMap<String, String>),
+    List(Vec<HashPair>),
+}
+
+fn normalize_hash(h: &str) -> String {
+    let h = h.trim().trim_matches('"').to_lowercase();
+    h.strip_prefix("0x").map(|s| s.to_string()).unwrap_or(h)
+}
+
+fn is_hex_hash(h: &str) -> bool {
+    !h.is_empty() && h.chars().all(|c| c.is_ascii_hexdigit())
+}
+
+// Accepts {"old": "new"}, [{"old": .., "new": ..}] or CSV "old,new" lines (header optional)
+fn load_hash_table(path: &Path) -> Result<HashMap<String, String>, String> {
+    let content = fs::read_to_string(path).map_err(|e| format!("Failed to read hash table: {}", e))?;
+    let is_json = path
+        .extension()
+        .map(|e| e.to_string_lossy().eq_ignore_ascii_case("json"))
+        .unwrap_or(false);
+
+    let pairs: Vec<(String, String)> = if is_json {
+        match serde_json::from_str::<HashTableJson>(&content)
+            .map_err(|e| format!("Failed to parse hash table: {}", e))?
+        {
+            HashTableJson::Map(map) => map.into_iter().collect(),
+            HashTableJson::List(list) => list.into_iter().map(|p| (p.old, p.new)).collect(),
+        }
+    } else {
+        content
+            .lines()
+            .filter_map(|line| {
+                let mut cols = line.split([',', ';', '\t']);
+                Some((cols.next()?.to_string(), cols.next()?.to_string()))
+            })
+            .collect()
+    };
+
+    let mut table = HashMap::new();
+    for (old, new) in pairs {
+        let (old, new) = (normalize_hash(&old), normalize_hash(&new));
+        // Silently skips CSV headers such as "old,new"
+        if !is_hex_hash(&old) || !is_hex_hash(&new) || old == new {
+            continue;
+        }
+        table.insert(old, new);
+    }
+
+    if table.is_empty() {
+        return Err("Hash table contains no valid old -> new pairs".to_string());
+    }
+    Ok(table)
+}
+
+fn collect_ini_files(dir: &Path, out: &mut Vec<PathBuf>) {
+    if let Ok(entries) = fs::read_dir(dir) {
+        for entry in entries.flatten() {
+            let path = entry.path();
+            if path.is_dir() {
+                collect_ini_files(&path, out);
+            } else if path
+                .extension()
+                .map(|e| e.to_string_lossy().eq_ignore_ascii_case("ini"))
+                .unwrap_or(false)
+            {
+                out.push(path);
+            }
+        }
+    }
+}
+
+fn relative_to(base: &Path, path: &Path) -> String {
+    path.strip_prefix(base).unwrap_or(path).to_string_lossy().replace('\\', "/")
+}
+
+// Returns the rewritten line if it is a "hash = <old>" line present in the table.
+// Only the hash itself is replaced; spacing and any "; comment" stay as they were.
+fn remap_line(line: &str, table: &HashMap<String, String>) -> Option<String> {
+    let trimmed = line.trim_start();
+    if trimmed.starts_with(';') || trimmed.starts_with('#') {
+        return None;
+    }
+    let eq_idx = line.find('=')?;
+    if !line[..eq_idx].trim().eq_ignore_ascii_case("hash") {
+        return None;
+    }
+
+    let after_eq = &line[eq_idx + 1..];
+    let code = &after_eq[..after_eq.find(';').unwrap_or(after_eq.len())];
+    let raw_value = code.trim();
+    let new_hash = table.get(&normalize_hash(raw_value))?;
+    let prefix = if raw_value.to_lowercase().starts_with("0x") { &raw_value[..2] } else { "" };
+
+    let value_start = eq_idx + 1 + (code.len() - code.trim_start().len());
+    let value_end = value_start + raw_value.len();
+    Some(format!("{}{}{}{}", &line[..value_start], prefix, new_hash, &line[value_end..]))
+}
+
+fn backups_root(install_dir: &Path) -> PathBuf {
+    install_dir.join(BACKUP_DIR)
+}
+
+#[tauri::command]
+pub fn remap_mod_hashes(
+    app: AppHandle,
+    game_name: String,
+    table_path: String,
+    target: Option<String>, // Mod or group relative to Mods; None = whole Mods tree
+    dry_run: bool,
+) -> Result<HashRemapResult, String> {
+    let install_dir = get_game_install_dir(&app, &game_name)?;
+    let table = load_hash_table(Path::new(&table_path))?;
+    remap_in(&install_dir, &table, target.unwrap_or_default(), dry_run)
+}
+
+fn remap_in(
+    install_dir: &Path,
+    table: &HashMap<String, String>,
+    target: String,
+    dry_run: bool,
+) -> Result<HashRemapResult, String> {
+    let mods_dir = install_dir.join("Mods");
+    let scan_root = if target.is_empty() || target == "Root" { mods_dir.clone() } else { join_inside(&mods_dir, &target)? };
+    if !scan_root.exists() {
+        return Err(format!("Remap target not found: {:?}", scan_root));
+    }
+
+    let mut files = Vec::new();
+    collect_ini_files(&scan_root, &mut files);
+    files.sort();
+
+    let mut changes = Vec::new();
+    let mut skipped = Vec::new();
+    let mut pending: Vec<(PathBuf, IniManager)> = Vec::new();
+
+    for file in files {
+        let mut ini = match IniManager::load(&file) {
+            Ok(ini) => ini,
+            Err(e) => {
+                eprintln!("[HashRemap] Skipping {:?}: {}", file, e);
+                continue;
+            }
+        };
+
+        let edits: Vec<(usize, String, String)> = ini
+            .content()
+            .lines()
+            .enumerate()
+            .filter_map(|(i, line)| remap_line(line, table).map(|new_line| (i + 1, line.to_string(), new_line)))
+            .collect();
+
+        if edits.is_empty() {
+            continue;
+        }
+        // Left out before anything is written, so one such file cannot stop the remap halfway
+        if ini.is_lossy() {
+            skipped.push(HashRemapSkipped {
+                file: relative_to(&mods_dir, &file),
+                reason: format!("its encoding could not be detected ({} lines not remapped)", edits.len()),
+            });
+            continue;
+        }
+
+        for (line, old_line, new_line) in edits {
+            ini.set_line(line, &new_line);
+            changes.push(HashRemapChange {
+                file: relative_to(&mods_dir, &file),
+                line,
+                old_line,
+                new_line,
+            });
+        }
+        pending.push((file, ini));
+    }
+
+    if dry_run || pending.is_empty() {
+        return Ok(HashRemapResult {
+            files_changed: pending.len(),
+            changes,
+            dry_run,
+            backup_id: None,
+            skipped,
+        });
+    }
+
+    // Back up every file before touching anything
+    let now = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap_or_default();
+    let created_at = now.as_secs();
+    let backup_id = now.as_millis().to_string();
+    let backup_dir = backups_root(install_dir).join(&backup_id);
+
+    let mut backup = HashRemapBackup {
+        id: backup_id.clone(),
+        created_at,
+        target: if target.is_empty() { "Root".to_string() } else { target },
+        files: Vec::new(),
+    };
+
+    for (file, _) in &pending {
+        let rel = relative_to(&mods_dir, file);
+        let dest = backup_dir.join(&rel);
+        if let Some(parent) = dest.parent() {
+            fs::create_dir_all(parent).map_err(|e| format!("Failed to create backup directory: {}", e))?;
+        }
+        fs::copy(file, &dest).map_err(|e| format!("Failed to back up {}: {}", rel, e))?;
+        backup.files.push(rel);
+    }
+
+    let manifest = serde_json::to_string_pretty(&backup).map_err(|e| format!("Failed to serialize manifest: {}", e))?;
+    fs::write(backup_dir.join(MANIFEST_NAME), manifest).map_err(|e| format!("Failed to write manifest: {}", e))?;
+
+    for (_, ini) in &pending {
+        // The files before this one are already rewritten; the backup undoes them
+        ini.save()
+            .map_err(|e| format!("{}. Files changed so far can be restored from backup {}", e, backup_id))?;
+    }
+
+    println!("[HashRemap] Rewrote {} lines in {} files, backup {}", changes.len(), pending.len(), backup_id);
+
+    Ok(HashRemapResult {
+        files_changed: pending.len(),
+        changes,
+        dry_run,
+        backup_id: Some(backup_id),
+        skipped,
+    })
+}
+
+#[tauri::command]
+pub fn list_hash_remap_backups(app: AppHandle, game_name: String) -> Result<Vec<HashRemapBackup>, String> {
+    let install_dir = get_game_install_dir(&app, &game_name)?;
+    let root = backups_root(&install_dir);
+
+    let mut backups = Vec::new();
+    if let Ok(entries) = fs::read_dir(&root) {
+        for entry in entries.flatten() {
+            let manifest = entry.path().join(MANIFEST_NAME);
+            if let Ok(content) = fs::read_to_string(&manifest) {
+                if let Ok(b) = serde_json::from_str::<HashRemapBackup>(&content) {
+                    backups.push(b);
+                }
+            }
+        }
+    }
+
+    // Newest first
+    backups.sort_by_key(|b| std::cmp::Reverse(b.created_at));
+    Ok(backups)
+}
+
+#[tauri::command]
+pub fn revert_hash_remap(app: AppHandle, game_name: String, backup_id: String) -> Result<usize, String> {
+    let install_dir = get_game_install_dir(&app, &game_name)?;
+    revert_in(&install_dir, &backup_id)
+}
+
+fn revert_in(install_dir: &Path, backup_id: &str) -> Result<usize, String> {
+    let mods_dir = install_dir.join("Mods");
+    // Ids are millisecond timestamps
+    if backup_id.is_empty() || !backup_id.chars().all(|c| c.is_ascii_digit()) {
+        return Err(format!("Invalid backup id: {}", backup_id));
+    }
+    let backup_dir = backups_root(install_dir).join(backup_id);
+
+    let content = fs::read_to_string(backup_dir.join(MANIFEST_NAME))
+        .map_err(|e| format!("Backup {} not found: {}", backup_id, e))?;
+    let backup: HashRemapBackup =
+        serde_json::from_str(&content).map_err(|e| format!("Failed to parse backup manifest: {}", e))?;
+
+    let mut restored = 0;
+    for rel in &backup.files {
+        let dest = join_inside(&mods_dir, rel)?;
+        if let Some(parent) = dest.parent() {
+            if !parent.exists() {
+                // Mod was moved or renamed since the remap, nothing sensible to restore into
+                eprintln!("[HashRemap] Skipping {}: target folder no longer exists", rel);
+                continue;
+            }
+        }
+        fs::copy(backup_dir.join(rel), &dest).map_err(|e| format!("Failed to restore {}: {}", rel, e))?;
+        restored += 1;
+    }
+
+    // Keep the backup around if anything could not be put back
+    if restored == backup.files.len() {
+        fs::remove_dir_all(&backup_dir).map_err(|e| format!("Failed to remove backup: {}", e))?;
+    }
+
+    println!("[HashRemap] Reverted backup {}: {} files restored", backup_id, restored);
+    Ok(restored)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn table() -> HashMap<String, String> {
+        HashMap::from([("aabbccdd".to_string(), "11223344".to_string())])
+    }
+
+    #[test]
+    fn remap_line_keeps_spacing_prefix_and_comments() {
+        let table = table();
+        assert_eq!(remap_line("hash=aabbccdd", &table).as_deref(), Some("hash=11223344"));
+        assert_eq!(remap_line("  Hash = 0XAABBCCDD ; body\r", &table).as_deref(), Some("  Hash = 0X11223344 ; body\r"));
+        assert_eq!(remap_line("hash = aabbccdd;old", &table).as_deref(), Some("hash = 11223344;old"));
+        assert_eq!(remap_line("; hash = aabbccdd", &table), None);
+        assert_eq!(remap_line("filter_index = aabbccdd", &table), None);
+        assert_eq!(remap_line("hash = deadbeef", &table), None);
+    }
+
+    #[test]
+    fn remap_backs_up_and_revert_restores() {
+        let root = std::env::temp_dir().join(format!("ssmt4-hashremap-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+        fs::create_dir_all(root.join("Mods/Ayaka")).unwrap();
+        let ini = root.join("Mods/Ayaka/Ayaka.ini");
+        let original = "[TextureOverrideBody]\nhash = aabbccdd ; body\n";
+        fs::write(&ini, original).unwrap();
+
+        assert!(remap_in(&root, &table(), "../".to_string(), false).is_err());
+
+        let preview = remap_in(&root, &table(), "Ayaka".to_string(), true).unwrap();
+        assert_eq!(preview.changes.len(), 1);
+        assert_eq!(fs::read_to_string(&ini).unwrap(), original);
+
+        let result = remap_in(&root, &table(), String::new(), false).unwrap();
+        assert_eq!(result.files_changed, 1);
+        assert_eq!(fs::read_to_string(&ini).unwrap(), "[TextureOverrideBody]\nhash = 11223344 ; body\n");
+
+        assert!(revert_in(&root, "../../x").is_err());
+        assert_eq!(revert_in(&root, &result.backup_id.unwrap()).unwrap(), 1);
+        assert_eq!(fs::read_to_string(&ini).unwrap(), original);
+        assert_eq!(fs::read_dir(backups_root(&root)).unwrap().count(), 0);
+        let _ = fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn undecodable_files_are_skipped_before_anything_is_written() {
+        let root = std::env::temp_dir().join(format!("ssmt4-hashremap-lossy-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+        fs::create_dir_all(root.join("Mods/A")).unwrap();
+        fs::create_dir_all(root.join("Mods/B")).unwrap();
+        let good = root.join("Mods/B/B.ini");
+        fs::write(&good, "[TextureOverrideBody]\nhash = aabbccdd\n").unwrap();
+        let bad = root.join("Mods/A/A.ini");
+        let bad_bytes = b"; \xff\xfe\xff\x80\x80\n[TextureOverrideBody]\nhash = aabbccdd\n";
+        fs::write(&bad, bad_bytes).unwrap();
+
+        let preview = remap_in(&root, &table(), String::new(), true).unwrap();
+        assert_eq!(preview.files_changed, 1);
+        assert_eq!(preview.skipped.len(), 1);
+        assert_eq!(preview.skipped[0].file, "A/A.ini");
+
+        let result = remap_in(&root, &table(), String::new(), false).unwrap();
+        assert!(result.backup_id.is_some());
+        assert_eq!(fs::read_to_string(&good).unwrap(), "[TextureOverrideBody]\nhash = 11223344\n");
+        assert_eq!(fs::read(&bad).unwrap(), bad_bytes);
+        let _ = fs::remove_dir_all(&root);
+    }
+}
@@ -0,0 +1,9 @@
+use crate::configs::game_config::get_game_install_dir;
+use crate::utils::ini_resolver::{self, EffectiveMigotoConfig};
+use tauri::AppHandle;
+
+#[tauri::command]
+pub fn resolve_3dmigoto_config(app: AppHandle, game_name: String) -> Result<EffectiveMigotoConfig, String> {
+    let install_dir = get_game_install_dir(&app, &game_name)?;
+    ini_resolver::resolve_includes(&install_dir)
+}
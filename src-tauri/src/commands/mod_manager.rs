@@ -1,4 +1,4 @@
-use crate::utils::file_manager::get_global_games_dir;
+use crate::configs::game_config::get_game_install_dir;
 use serde::{Deserialize, Serialize};
 use std::fs;
 use std::path::{Path, PathBuf};
@@ -42,41 +42,6 @@ pub struct ModInfo {
     pub last_modified: u64, // Timestamp
 }
 
-#[derive(Deserialize)]
-struct GameConfigPartial {
-    #[serde(rename = "threeDMigoto")]
-    three_d_migoto: Option<ThreeDMigotoPartial>,
-}
-
-#[derive(Deserialize)]
-struct ThreeDMigotoPartial {
-    #[serde(rename = "installDir")]
-    install_dir: Option<String>,
-}
-
-fn get_game_install_dir(app: &AppHandle, game_name: &str) -> Result<PathBuf, String> {
-    let games_dir = get_global_games_dir(app);
-    let config_path = games_dir.join(game_name).join("Config.json");
-
-    if !config_path.exists() {
-        return Err(format!("Config file not found: {:?}", config_path));
-    }
-
-    let config_content = fs::read_to_string(&config_path)
-        .map_err(|e| format!("Failed to read config: {}", e))?;
-
-    let config: GameConfigPartial = serde_json::from_str(&config_content)
-        .map_err(|e| format!("Failed to parse config: {}", e))?;
-
-    if let Some(tdm) = config.three_d_migoto {
-        if let Some(dir) = tdm.install_dir {
-            return Ok(PathBuf::from(dir));
-        }
-    }
-    
-    Err("3Dmigoto install directory not configured".to_string())
-}
-
 fn find_preview_images(path: &Path) -> Vec<String> {
     let mut images = Vec::new();
     if let Ok(entries) = fs::read_dir(path) {
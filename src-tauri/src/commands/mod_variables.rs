@@ -0,0 +1,389 @@
+use crate::configs::game_config::get_game_install_dir;
+use crate::utils::atomic_file;
+use crate::utils::file_manager::join_inside;
+use crate::utils::ini_manager::IniManager;
+use crate::utils::ini_resolver;
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use tauri::AppHandle;
+
+// 3Dmigoto stores persisted values in d3dx_user.ini as:
+//   [Constants]
+//   $\mods\ayaka\ayaka.ini\swapvar = 1
+// i.e. "$\" + namespace + "\" + variable name, all lowercase. The namespace is the
+// one the ini declares with "namespace = ...", or else its path relative to the install dir.
+const USER_INI: &str = "d3dx_user.ini";
+
+#[derive(Debug, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct ModVariable {
+    pub name: String,               // "$swapvar"
+    pub default_value: String,      // From "global persist $swapvar = 0"
+    pub persisted_value: Option<String>,
+    pub user_key: String,           // Key used in d3dx_user.ini
+}
+
+#[derive(Debug, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct ModVariables {
+    pub ini_file: String,  // Relative to install dir, e.g. "Mods/Ayaka/Ayaka.ini"
+    pub mod_path: String,  // Relative to Mods, e.g. "Ayaka"
+    pub encoding: String,
+    pub variables: Vec<ModVariable>,
+}
+
+fn path_namespace(install_dir: &Path, ini_path: &Path) -> Option<String> {
+    let rel = ini_path.strip_prefix(install_dir).ok()?;
+    Some(rel.to_string_lossy().replace('/', "\\").to_lowercase())
+}
+
+// "namespace = ..." before the first section, as 3Dmigoto reads it
+fn declared_namespace(content: &str) -> Option<String> {
+    for line in content.lines() {
+        let line = line.trim();
+        if line.starts_with('[') {
+            break;
+        }
+        let Some((key, value)) = line.split_once('=') else { continue };
+        if key.trim().eq_ignore_ascii_case("namespace") {
+            let value = value.trim().replace('/', "\\").to_lowercase();
+            return (!value.is_empty()).then_some(value);
+        }
+    }
+    None
+}
+
+fn namespace_of(install_dir: &Path, ini_path: &Path, content: &str) -> Option<String> {
+    declared_namespace(content).or_else(|| path_namespace(install_dir, ini_path))
+}
+
+// Namespace of a mod ini that may no longer exist, e.g. when resetting a deleted mod
+fn namespace_of_file(install_dir: &Path, ini_path: &Path) -> Option<String> {
+    let content = fs::read(ini_path).map(|b| String::from_utf8_lossy(&b).into_owned()).unwrap_or_default();
+    namespace_of(install_dir, ini_path, &content)
+}
+
+fn user_key(namespace: &str, var_name: &str) -> String {
+    format!("$\\{}\\{}", namespace, var_name.trim_start_matches('$').to_lowercase())
+}
+
+// "global persist $x", "persist global $x" -> Some("$x")
+fn persist_var_name(key: &str) -> Option<String> {
+    let tokens: Vec<&str> = key.split_whitespace().collect();
+    let name = tokens.last()?;
+    if name.starts_with('$') && tokens.iter().any(|t| t.eq_ignore_ascii_case("persist")) {
+        Some(name.to_string())
+    } else {
+        None
+    }
+}
+
+// Declarations are read from the raw text: "global persist $x" without a
+// default has no "=", so it never shows up as an ini entry.
+// Returns (name, default) for every persist declaration in [Constants].
+fn persist_declarations(content: &str) -> Vec<(String, String)> {
+    let mut in_constants = false;
+    let mut found = Vec::new();
+    for line in content.lines() {
+        let line = line.trim();
+        if line.starts_with('[') {
+            in_constants = line.trim_start_matches('[').trim_end_matches(']').trim().eq_ignore_ascii_case("Constants");
+            continue;
+        }
+        if !in_constants || line.is_empty() || line.starts_with(';') || line.starts_with('#') {
+            continue;
+        }
+        let (declaration, default) = match line.split_once('=') {
+            Some((left, right)) => (left, right.split(';').next().unwrap_or("").trim()),
+            // 3Dmigoto starts a variable declared without a value at 0
+            None => (line, "0"),
+        };
+        if let Some(name) = persist_var_name(declaration) {
+            found.push((name, default.to_string()));
+        }
+    }
+    found
+}
+
+// ini_file comes from the frontend and is relative to the game folder
+fn mod_ini_path(install_dir: &Path, ini_file: &str) -> Result<PathBuf, String> {
+    let ini_path = join_inside(install_dir, ini_file)?;
+    let mods_dir = install_dir.join("Mods");
+    if !ini_path.starts_with(&mods_dir) {
+        return Err(format!("Mod ini is outside the Mods folder: {}", ini_file));
+    }
+    // A junction inside Mods can still point elsewhere
+    join_inside(&mods_dir, &ini_path.strip_prefix(&mods_dir).unwrap_or(&ini_path).to_string_lossy())
+}
+
+// Namespaces declared by the ini files under dir, DISABLED ones included
+fn collect_declared_namespaces(dir: &Path, found: &mut HashSet<String>) {
+    let Ok(entries) = fs::read_dir(dir) else { return };
+    for entry in entries.flatten() {
+        let path = entry.path();
+        if path.is_dir() {
+            collect_declared_namespaces(&path, found);
+        } else if path.extension().is_some_and(|e| e.eq_ignore_ascii_case("ini")) {
+            if let Ok(bytes) = fs::read(&path) {
+                found.extend(declared_namespace(&String::from_utf8_lossy(&bytes)));
+            }
+        }
+    }
+}
+
+// A path namespace that is gone, or inside a DISABLED folder / file that 3Dmigoto
+// no longer loads. Declared namespaces are not paths, so only one that no mod
+// declares any more and that looks like an ini path can be judged.
+fn is_stale_namespace(install_dir: &Path, namespace: &str, declared: &HashSet<String>) -> bool {
+    if declared.contains(namespace) || !namespace.ends_with(".ini") {
+        return false;
+    }
+    namespace.split('\\').any(ini_resolver::is_disabled_name) || !namespace_exists(install_dir, namespace)
+}
+
+// Namespaces are lowercased, so match them case-insensitively (matters under Wine)
+fn namespace_exists(install_dir: &Path, namespace: &str) -> bool {
+    let mut current = install_dir.to_path_buf();
+    for part in namespace.split('\\').filter(|p| !p.is_empty()) {
+        let exact = current.join(part);
+        if exact.exists() {
+            current = exact;
+            continue;
+        }
+        let found = fs::read_dir(&current).ok().and_then(|rd| {
+            rd.flatten()
+                .map(|e| e.path())
+                .find(|p| p.file_name().map(|n| n.to_string_lossy().eq_ignore_ascii_case(part)).unwrap_or(false))
+        });
+        match found {
+            Some(p) => current = p,
+            None => return false,
+        }
+    }
+    true
+}
+
+fn load_user_ini(install_dir: &Path) -> Result<IniManager, String> {
+    let path = install_dir.join(USER_INI);
+    if !path.exists() {
+        fs::write(&path, "[Constants]\n").map_err(|e| format!("Failed to create {}: {}", USER_INI, e))?;
+    }
+    IniManager::load(&path)
+}
+
+fn read_persisted(install_dir: &Path) -> HashMap<String, String> {
+    let mut values = HashMap::new();
+    let path = install_dir.join(USER_INI);
+    if let Ok(ini) = IniManager::load(&path) {
+        for section in ini.sections() {
+            if !section.name.eq_ignore_ascii_case("Constants") {
+                continue;
+            }
+            for entry in section.entries {
+                values.insert(entry.key.to_lowercase(), entry.value);
+            }
+        }
+    }
+    values
+}
+
+#[tauri::command]
+pub fn list_mod_variables(app: AppHandle, game_name: String) -> Result<Vec<ModVariables>, String> {
+    let install_dir = get_game_install_dir(&app, &game_name)?;
+    let mods_dir = install_dir.join("Mods");
+    let persisted = read_persisted(&install_dir);
+
+    // Only what 3Dmigoto actually loads can have persisted state
+    let resolved = ini_resolver::resolve_includes(&install_dir)?;
+    let mut result = Vec::new();
+
+    for loaded in resolved.loaded_files {
+        let ini_path = PathBuf::from(&loaded.path);
+        if !ini_path.starts_with(&mods_dir) {
+            continue;
+        }
+        let Ok(ini) = IniManager::load(&ini_path) else { continue };
+        let Some(namespace) = namespace_of(&install_dir, &ini_path, ini.content()) else { continue };
+
+        let variables: Vec<ModVariable> = persist_declarations(ini.content())
+            .into_iter()
+            .map(|(name, default_value)| {
+                let key = user_key(&namespace, &name);
+                ModVariable {
+                    persisted_value: persisted.get(&key).cloned(),
+                    name,
+                    default_value,
+                    user_key: key,
+                }
+            })
+            .collect();
+
+        if variables.is_empty() {
+            continue;
+        }
+
+        let mod_path = ini_path
+            .parent()
+            .and_then(|p| p.strip_prefix(&mods_dir).ok())
+            .map(|p| p.to_string_lossy().replace('\\', "/"))
+            .unwrap_or_default();
+
+        result.push(ModVariables {
+            ini_file: ini_path.strip_prefix(&install_dir).unwrap_or(&ini_path).to_string_lossy().replace('\\', "/"),
+            mod_path,
+            encoding: loaded.encoding,
+            variables,
+        });
+    }
+
+    Ok(result)
+}
+
+#[tauri::command]
+pub fn set_mod_variable(
+    app: AppHandle,
+    game_name: String,
+    ini_file: String, // As returned by list_mod_variables
+    name: String,
+    value: String,
+) -> Result<(), String> {
+    let install_dir = get_game_install_dir(&app, &game_name)?;
+    let ini_path = mod_ini_path(&install_dir, &ini_file)?;
+    if !ini_path.exists() {
+        return Err(format!("Mod ini not found: {:?}", ini_path));
+    }
+
+    // 3Dmigoto variables are floats
+    value
+        .trim()
+        .parse::<f32>()
+        .map_err(|_| format!("Invalid value for {}: '{}' is not a number", name, value))?;
+
+    let namespace = namespace_of_file(&install_dir, &ini_path).ok_or("Mod ini is outside the install directory")?;
+    let _lock = atomic_file::lock(&install_dir.join(USER_INI));
+    let mut user_ini = load_user_ini(&install_dir)?;
+    user_ini.set("Constants", &user_key(&namespace, &name), value.trim());
+    user_ini.save()
+}
+
+#[tauri::command]
+pub fn reset_mod_variable(app: AppHandle, game_name: String, ini_file: String, name: String) -> Result<(), String> {
+    let install_dir = get_game_install_dir(&app, &game_name)?;
+    // May no longer exist, e.g. resetting the value of a deleted mod
+    let ini_path = mod_ini_path(&install_dir, &ini_file)?;
+    let namespace = namespace_of_file(&install_dir, &ini_path).ok_or("Mod ini is outside the install directory")?;
+
+    if !install_dir.join(USER_INI).exists() {
+        return Ok(());
+    }
+
+    // Without a persisted entry 3Dmigoto falls back to the declared default
+    let _lock = atomic_file::lock(&install_dir.join(USER_INI));
+    let mut user_ini = load_user_ini(&install_dir)?;
+    user_ini.remove_key("Constants", &user_key(&namespace, &name));
+    user_ini.save()
+}
+
+#[tauri::command]
+pub fn prune_mod_variables(app: AppHandle, game_name: String) -> Result<Vec<String>, String> {
+    let install_dir = get_game_install_dir(&app, &game_name)?;
+    if !install_dir.join(USER_INI).exists() {
+        return Ok(Vec::new());
+    }
+
+    let _lock = atomic_file::lock(&install_dir.join(USER_INI));
+    let mut user_ini = load_user_ini(&install_dir)?;
+    let removed = stale_keys(&install_dir, &user_ini);
+
+    for key in &removed {
+        user_ini.remove_key("Constants", key);
+    }
+    if !removed.is_empty() {
+        user_ini.save()?;
+    }
+
+    println!("[ModVariables] Pruned {} stale entries", removed.len());
+    Ok(removed)
+}
+
+fn stale_keys(install_dir: &Path, user_ini: &IniManager) -> Vec<String> {
+    let mut declared = HashSet::new();
+    collect_declared_namespaces(&install_dir.join("Mods"), &mut declared);
+    let mut removed = Vec::new();
+
+    for section in user_ini.sections() {
+        if !section.name.eq_ignore_ascii_case("Constants") {
+            continue;
+        }
+        for entry in section.entries {
+            // "$\mods\foo\foo.ini\var" -> "mods\foo\foo.ini"
+            let Some(rest) = entry.key.strip_prefix("$\\") else { continue };
+            let Some((namespace, _var)) = rest.rsplit_once('\\') else { continue };
+            if is_stale_namespace(install_dir, &namespace.to_lowercase(), &declared) {
+                removed.push(entry.key.clone());
+            }
+        }
+    }
+    removed
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn finds_persist_declarations_with_and_without_defaults() {
+        let content = "[Constants]\nglobal persist $swapvar = 1 ; outfit\nglobal $plain = 2\npersist global $noval\n; global persist $commented = 3\n[Present]\nglobal persist $elsewhere = 4\n";
+        assert_eq!(
+            persist_declarations(content),
+            [("$swapvar".to_string(), "1".to_string()), ("$noval".to_string(), "0".to_string())]
+        );
+    }
+
+    #[test]
+    fn mod_ini_paths_stay_inside_mods_and_disabled_mods_are_stale() {
+        let root = std::env::temp_dir().join(format!("ssmt4-modvars-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+        fs::create_dir_all(root.join("Mods/Ayaka")).unwrap();
+        fs::create_dir_all(root.join("Mods/DISABLED_Old")).unwrap();
+        fs::write(root.join("Mods/Ayaka/Ayaka.ini"), "").unwrap();
+
+        assert!(mod_ini_path(&root, "Mods/Ayaka/Ayaka.ini").is_ok());
+        assert!(mod_ini_path(&root, "Mods/../d3dx.ini").is_err());
+        assert!(mod_ini_path(&root, "d3dx.ini").is_err());
+        let absolute = root.join("Mods/Ayaka/Ayaka.ini").to_string_lossy().to_string();
+        assert!(mod_ini_path(&root, &absolute).is_err());
+
+        let declared = HashSet::new();
+        assert!(!is_stale_namespace(&root, "mods\\ayaka\\ayaka.ini", &declared));
+        assert!(is_stale_namespace(&root, "mods\\disabled_old\\old.ini", &declared));
+        assert!(is_stale_namespace(&root, "mods\\gone\\gone.ini", &declared));
+        let _ = fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn namespaced_mods_key_by_their_namespace_and_survive_pruning() {
+        let root = std::env::temp_dir().join(format!("ssmt4-modvars-ns-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+        fs::create_dir_all(root.join("Mods/Raiden")).unwrap();
+        let raiden = root.join("Mods/Raiden/Raiden.ini");
+        fs::write(&raiden, "namespace = RaidenShogun/Outfits\n\n[Constants]\nglobal persist $swapvar = 0\n").unwrap();
+        fs::write(
+            root.join(USER_INI),
+            "[Constants]\n$\\raidenshogun\\outfits\\swapvar = 2\n$\\mods\\gone\\gone.ini\\swapvar = 1\n$\\someothernamespace\\x = 1\n",
+        )
+        .unwrap();
+
+        let namespace = namespace_of_file(&root, &raiden).unwrap();
+        assert_eq!(namespace, "raidenshogun\\outfits");
+        assert_eq!(user_key(&namespace, "$SwapVar"), "$\\raidenshogun\\outfits\\swapvar");
+        assert_eq!(read_persisted(&root).get(&user_key(&namespace, "$swapvar")).map(String::as_str), Some("2"));
+        assert_eq!(declared_namespace("[Constants]\nnamespace = late\n"), None);
+
+        let user_ini = IniManager::load(root.join(USER_INI)).unwrap();
+        assert_eq!(stale_keys(&root, &user_ini), ["$\\mods\\gone\\gone.ini\\swapvar"]);
+        let _ = fs::remove_dir_all(&root);
+    }
+}
@@ -1,7 +1,9 @@
 use tauri::{AppHandle, Manager, Runtime};
 use std::process::Command;
+#[cfg(windows)]
 use std::os::windows::process::CommandExt; // 仅限 Windows 平台，用于隐藏控制台窗口
 
+#[cfg(windows)]
 const CREATE_NO_WINDOW: u32 = 0x08000000;
 
 #[tauri::command]
@@ -25,9 +27,12 @@ pub fn run_resource_executable<R: Runtime>(
     }
 
     // 3. 执行命令
-    let output = Command::new(&exe_path)
-        .args(args)
-        .creation_flags(CREATE_NO_WINDOW) // 默默执行，不跳出黑框
+    let mut command = Command::new(&exe_path);
+    command.args(args);
+    #[cfg(windows)]
+    command.creation_flags(CREATE_NO_WINDOW); // 默默执行，不跳出黑框
+
+    let output = command
         .output()
         .map_err(|e| format!("执行失败: {}", e))?;
 
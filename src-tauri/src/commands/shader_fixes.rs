@@ -0,0 +1,245 @@
+use crate::configs::game_config::get_game_install_dir;
+use crate::utils::ini_manager::IniManager;
+use crate::utils::ini_resolver::{self, is_disabled_name};
+use serde::Serialize;
+use std::collections::HashSet;
+use std::fs;
+use std::path::Path;
+use tauri::AppHandle;
+
+#[derive(Debug, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct ShaderFixInfo {
+    pub file_name: String,   // Name on disk, including any DISABLED_ prefix
+    pub hash: String,        // 16 hex digits, lowercase
+    pub shader_type: String, // "vs", "ps", "cs", "gs", "hs", "ds"
+    pub format: String,      // "hlsl", "asm" or "bin"
+    pub enabled: bool,
+    pub referenced: bool,    // A loaded ini has a "hash =" pointing at this shader
+    pub size: u64,
+}
+
+// 3Dmigoto naming:
+//   <hash>-<type>_replace.txt  HLSL
+//   <hash>-<type>.txt          Assembly
+//   <hash>-<type>_replace.bin  Compiled HLSL (also <hash>-<type>.bin for assembly)
+fn parse_shader_fix_name(name: &str) -> Option<(String, String, String)> {
+    let lower = name.to_lowercase();
+    let (stem, ext) = lower.rsplit_once('.')?;
+    let (hash, rest) = stem.split_once('-')?;
+    if hash.len() != 16 || !hash.chars().all(|c| c.is_ascii_hexdigit()) {
+        return None;
+    }
+
+    let (shader_type, replaced) = match rest.strip_suffix("_replace") {
+        Some(t) => (t, true),
+        None => (rest, false),
+    };
+    if !["vs", "ps", "cs", "gs", "hs", "ds"].contains(&shader_type) {
+        return None;
+    }
+
+    let format = match (ext, replaced) {
+        ("txt", true) => "hlsl",
+        ("txt", false) => "asm",
+        ("bin", _) => "bin",
+        _ => return None,
+    };
+
+    Some((hash.to_string(), shader_type.to_string(), format.to_string()))
+}
+
+fn strip_disabled(name: &str) -> &str {
+    if !is_disabled_name(name) {
+        return name;
+    }
+    // is_disabled_name checked the first 8 bytes are ASCII, so slicing there is safe
+    let rest = &name[8..];
+    rest.strip_prefix('_').unwrap_or(rest)
+}
+
+// Every "hash = ..." value from the ini files 3Dmigoto would load
+fn referenced_hashes(install_dir: &Path) -> HashSet<String> {
+    let mut hashes = HashSet::new();
+    let Ok(resolved) = ini_resolver::resolve_includes(install_dir) else {
+        return hashes;
+    };
+
+    for loaded in resolved.loaded_files {
+        let Ok(ini) = IniManager::load(&loaded.path) else { continue };
+        for section in ini.sections() {
+            for entry in section.entries {
+                if entry.key.eq_ignore_ascii_case("hash") {
+                    let value = entry.value.to_lowercase();
+                    hashes.insert(value.strip_prefix("0x").unwrap_or(&value).to_string());
+                }
+            }
+        }
+    }
+    hashes
+}
+
+#[tauri::command]
+pub fn scan_shader_fixes(app: AppHandle, game_name: String) -> Result<Vec<ShaderFixInfo>, String> {
+    let install_dir = get_game_install_dir(&app, &game_name)?;
+    let fixes_dir = install_dir.join("ShaderFixes");
+    if !fixes_dir.exists() {
+        return Ok(Vec::new());
+    }
+
+    let referenced = referenced_hashes(&install_dir);
+    let mut fixes = Vec::new();
+
+    let entries = fs::read_dir(&fixes_dir).map_err(|e| format!("Failed to read ShaderFixes: {}", e))?;
+    for entry in entries.flatten() {
+        let path = entry.path();
+        if !path.is_file() {
+            continue;
+        }
+        let file_name = entry.file_name().to_string_lossy().to_string();
+        let Some((hash, shader_type, format)) = parse_shader_fix_name(strip_disabled(&file_name)) else {
+            continue; // Helper includes such as .hlsl headers are not fixes
+        };
+
+        fixes.push(ShaderFixInfo {
+            enabled: !is_disabled_name(&file_name),
+            referenced: referenced.contains(&hash),
+            size: entry.metadata().map(|m| m.len()).unwrap_or(0),
+            file_name,
+            hash,
+            shader_type,
+            format,
+        });
+    }
+
+    fixes.sort_by(|a, b| a.hash.cmp(&b.hash).then(a.file_name.cmp(&b.file_name)));
+    Ok(fixes)
+}
+
+// Toggles every file of the fix for that hash and shader type together (the
+// .txt and its .bin cache), since a .bin left enabled would still be loaded.
+// Returns the new name of file_name.
+#[tauri::command]
+pub fn toggle_shader_fix(app: AppHandle, game_name: String, file_name: String, enable: bool) -> Result<String, String> {
+    let install_dir = get_game_install_dir(&app, &game_name)?;
+    toggle_in(&install_dir.join("ShaderFixes"), &file_name, enable)
+}
+
+fn toggle_in(fixes_dir: &Path, file_name: &str, enable: bool) -> Result<String, String> {
+    let current = fixes_dir.join(file_name);
+    if !current.is_file() || current.parent() != Some(fixes_dir) {
+        return Err(format!("Shader fix not found: {}", file_name));
+    }
+    let Some((hash, shader_type, _)) = parse_shader_fix_name(strip_disabled(file_name)) else {
+        return Err(format!("Not a shader fix: {}", file_name));
+    };
+
+    // Same convention as mods: a DISABLED_ prefix keeps 3Dmigoto from matching the hash
+    let mut renames = Vec::new();
+    let entries = fs::read_dir(fixes_dir).map_err(|e| format!("Failed to read ShaderFixes: {}", e))?;
+    for entry in entries.flatten() {
+        let name = entry.file_name().to_string_lossy().to_string();
+        if !entry.path().is_file() || is_disabled_name(&name) != enable {
+            continue;
+        }
+        let same_fix = parse_shader_fix_name(strip_disabled(&name))
+            .is_some_and(|(h, t, _)| h == hash && t == shader_type);
+        if !same_fix {
+            continue;
+        }
+        let new_name = if enable { strip_disabled(&name).to_string() } else { format!("DISABLED_{}", name) };
+        renames.push((name, new_name));
+    }
+
+    // Nothing is renamed unless every file can be
+    for (_, new_name) in &renames {
+        if fixes_dir.join(new_name).exists() {
+            return Err(format!("{} already exists in ShaderFixes", new_name));
+        }
+    }
+    for (i, (name, new_name)) in renames.iter().enumerate() {
+        if let Err(e) = fs::rename(fixes_dir.join(name), fixes_dir.join(new_name)) {
+            for (done, done_new) in &renames[..i] {
+                let _ = fs::rename(fixes_dir.join(done_new), fixes_dir.join(done));
+            }
+            return Err(format!("Failed to rename shader fix {}: {}", name, e));
+        }
+    }
+
+    Ok(renames
+        .into_iter()
+        .find(|(name, _)| name == file_name)
+        .map(|(_, new_name)| new_name)
+        .unwrap_or_else(|| file_name.to_string()))
+}
+
+#[tauri::command]
+pub fn clear_shader_cache(app: AppHandle, game_name: String) -> Result<usize, String> {
+    let install_dir = get_game_install_dir(&app, &game_name)?;
+    let cache_dir = install_dir.join("ShaderCache");
+    if !cache_dir.exists() {
+        return Ok(0);
+    }
+
+    let mut removed = 0;
+    let entries = fs::read_dir(&cache_dir).map_err(|e| format!("Failed to read ShaderCache: {}", e))?;
+    for entry in entries.flatten() {
+        let path = entry.path();
+        let result = if path.is_dir() { fs::remove_dir_all(&path) } else { fs::remove_file(&path) };
+        match result {
+            Ok(_) => removed += 1,
+            Err(e) => return Err(format!("Failed to remove {:?}: {}. Please ensure the game is closed.", path, e)),
+        }
+    }
+
+    println!("[ShaderFixes] Cleared {} entries from {:?}", removed, cache_dir);
+    Ok(removed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn strip_disabled_handles_prefixes_and_non_ascii_names() {
+        assert_eq!(strip_disabled("DISABLED_a1b2c3d4e5f6a7b8-ps_replace.txt"), "a1b2c3d4e5f6a7b8-ps_replace.txt");
+        assert_eq!(strip_disabled("disabledа1.txt"), "а1.txt");
+        assert_eq!(strip_disabled("Ayaka_描述.txt"), "Ayaka_描述.txt");
+        assert_eq!(strip_disabled("禁用DISABLED.txt"), "禁用DISABLED.txt");
+        assert_eq!(strip_disabled("ıDISABLED_x.txt"), "ıDISABLED_x.txt");
+        assert_eq!(strip_disabled("DISABLE"), "DISABLE");
+    }
+
+    #[test]
+    fn toggles_every_file_of_a_fix_together() {
+        let dir = std::env::temp_dir().join(format!("ssmt4-shaderfixes-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        for name in ["a1b2c3d4e5f6a7b8-ps_replace.txt", "a1b2c3d4e5f6a7b8-ps_replace.bin", "a1b2c3d4e5f6a7b8-vs_replace.txt"] {
+            fs::write(dir.join(name), b"").unwrap();
+        }
+
+        let new_name = toggle_in(&dir, "a1b2c3d4e5f6a7b8-ps_replace.txt", false).unwrap();
+        assert_eq!(new_name, "DISABLED_a1b2c3d4e5f6a7b8-ps_replace.txt");
+        assert!(dir.join("DISABLED_a1b2c3d4e5f6a7b8-ps_replace.bin").is_file());
+        assert!(dir.join("a1b2c3d4e5f6a7b8-vs_replace.txt").is_file());
+
+        // A leftover enabled copy blocks enabling, and nothing is renamed
+        fs::write(dir.join("a1b2c3d4e5f6a7b8-ps_replace.bin"), b"").unwrap();
+        assert!(toggle_in(&dir, "DISABLED_a1b2c3d4e5f6a7b8-ps_replace.txt", true).is_err());
+        assert!(dir.join("DISABLED_a1b2c3d4e5f6a7b8-ps_replace.txt").is_file());
+        assert!(dir.join("DISABLED_a1b2c3d4e5f6a7b8-ps_replace.bin").is_file());
+
+        fs::remove_file(dir.join("a1b2c3d4e5f6a7b8-ps_replace.bin")).unwrap();
+        toggle_in(&dir, "DISABLED_a1b2c3d4e5f6a7b8-ps_replace.bin", true).unwrap();
+        assert!(dir.join("a1b2c3d4e5f6a7b8-ps_replace.txt").is_file());
+        assert!(dir.join("a1b2c3d4e5f6a7b8-ps_replace.bin").is_file());
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn parses_disabled_shader_fix_names() {
+        let parsed = parse_shader_fix_name(strip_disabled("DISABLED_a1b2c3d4e5f6a7b8-ps_replace.txt"));
+        assert_eq!(parsed.map(|(hash, _, _)| hash).as_deref(), Some("a1b2c3d4e5f6a7b8"));
+    }
+}
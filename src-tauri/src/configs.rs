@@ -1 +1,6 @@
 pub mod app_config;
+pub mod game_config;
+pub mod migrations;
+pub mod presets;
+pub mod recovery;
+pub mod validation;
@@ -2,12 +2,14 @@ use serde::{Deserialize, Serialize};
 use std::fs;
 use std::path::PathBuf;
 use crate::commands::game_scanner::BGType;
-use crate::utils::file_manager;
+use crate::configs::{migrations, recovery};
+use crate::utils::{atomic_file, file_manager};
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 #[serde(rename_all = "camelCase")] // Match JS conventions
 #[serde(default)]
 pub struct AppConfig {
+    pub schema_version: u32,
     pub bg_type: BGType,
     pub bg_image: String,
     pub bg_video: String,
@@ -19,25 +21,25 @@ pub struct AppConfig {
     pub current_config_name: String,
     pub window_width: f64,
     pub window_height: f64,
-    #[serde(default)]
     pub github_token: String,
     
-    // Page Visibility Settings
-    #[serde(default)]
+    // Page Visibility Settings. The struct-level default fills keys added after
+    // a settings.json was written, so these keep their Default values (show_mods: true)
     pub show_mods: bool,
-    #[serde(default)]
     pub show_workbench: bool,
-    #[serde(default)]
     pub show_stickers: bool,
-    #[serde(default)]
     pub show_websites: bool,
-    #[serde(default)]
     pub show_documents: bool,
 }
 
+// Whether settings.json failed to load at startup (e.g. a newer release wrote it).
+// The defaults used instead must not overwrite it when the window closes.
+pub struct SettingsLoadFailed(pub bool);
+
 impl Default for AppConfig {
     fn default() -> Self {
         Self {
+            schema_version: migrations::APP_CONFIG.current_version(),
             bg_type: BGType::Image,
             bg_image: "".to_string(), 
             bg_video: "".to_string(),
@@ -80,16 +82,17 @@ impl AppConfig {
         let mut config = if let Some(path) = Self::get_config_path() {
             println!("Loading settings from: {:?}", path);
             if path.exists() {
-                let content = fs::read_to_string(&path)
-                    .map_err(|e| format!("Failed to read settings file: {}", e))?;
+                let content = recovery::read_lossy(&path)?;
                 
                 println!("Settings content: {}", content);
-                match serde_json::from_str::<Self>(&content) {
-                    Ok(c) => c,
-                    Err(e) => {
-                         println!("Failed to parse settings, using defaults: {}", e);
-                         Self::default()
-                    }
+                // A damaged file is moved aside and what can be read is kept, so the
+                // defaults never overwrite it (the close handler saves right away)
+                if let Some(e) = recovery::find_damage::<Self>(&content, Some(&migrations::APP_CONFIG))? {
+                    println!("Failed to parse settings, recovering: {}", e);
+                    recovery::recover::<Self>(&path, &content, &e, Some(&migrations::APP_CONFIG))?
+                } else {
+                    migrations::load_and_migrate(&migrations::APP_CONFIG, &path, &content)
+                        .and_then(|value| serde_json::from_value::<Self>(value).map_err(|e| e.to_string()))?
                 }
             } else {
                 println!("Settings file does not exist, using defaults");
@@ -121,7 +124,7 @@ impl AppConfig {
             let content = serde_json::to_string_pretty(self)
                 .map_err(|e| format!("Serialization error: {}", e))?;
             
-            fs::write(path, content)
+            atomic_file::write(&path, content)
                 .map_err(|e| format!("File write error: {}", e))?;
             Ok(())
         } else {
@@ -0,0 +1,401 @@
+use crate::commands::game_scanner::BGType;
+use crate::configs::app_config::AppConfig;
+use crate::configs::migrations;
+use crate::configs::presets;
+use crate::configs::recovery;
+use crate::utils::atomic_file;
+use crate::utils::file_manager::get_global_games_dir;
+use crate::utils::hooks::HookCommand;
+use serde::{Deserialize, Deserializer, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use tauri::{AppHandle, Manager};
+
+// The one model of Games/<game>/Config.json. Every command reads and writes the
+// file through GameConfig, and derives folders through GameConfig::migoto_dir.
+// Keys this model does not know about are kept in `extra` so saving never drops them.
+
+pub const CONFIG_FILE_NAME: &str = "Config.json";
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct BasicSettings {
+    pub game_preset: String,
+    #[serde(default = "default_bg_type")]
+    pub background_type: BGType,
+}
+
+fn default_bg_type() -> BGType {
+    BGType::Image
+}
+
+impl Default for BasicSettings {
+    fn default() -> Self {
+        Self {
+            game_preset: presets::default_preset_id(),
+            background_type: BGType::Image,
+        }
+    }
+}
+
+// Number inputs in the settings form send "" when cleared
+fn lenient_i32<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<i32>, D::Error> {
+    let value = Option::<serde_json::Value>::deserialize(deserializer)?;
+    match value {
+        None | Some(serde_json::Value::Null) => Ok(None),
+        Some(serde_json::Value::Number(n)) => n
+            .as_i64()
+            .or_else(|| n.as_f64().map(|f| f as i64))
+            .and_then(|n| i32::try_from(n).ok())
+            .map(Some)
+            .ok_or_else(|| serde::de::Error::custom(format!("{} is out of range", n))),
+        Some(serde_json::Value::String(s)) if s.trim().is_empty() => Ok(None),
+        Some(serde_json::Value::String(s)) => s
+            .trim()
+            .parse()
+            .map(Some)
+            .map_err(|_| serde::de::Error::custom(format!("\"{}\" is not a whole number", s))),
+        Some(other) => Err(serde::de::Error::custom(format!("expected a number, got {}", other))),
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct ThreeDMigotoConfig {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub install_dir: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub target_exe_path: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub launcher_exe_path: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub launch_args: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub use_shell: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub show_error_popup: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub auto_set_analyse_options: Option<bool>,
+    #[serde(default, deserialize_with = "lenient_i32", skip_serializing_if = "Option::is_none")]
+    pub delay: Option<i32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub extra_dll: Option<String>,
+    #[serde(default, deserialize_with = "lenient_i32", skip_serializing_if = "Option::is_none")]
+    pub auto_exit_seconds: Option<i32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub use_upx: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub launch_mode: Option<String>, // "windows", "wine" or "proton"
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub wine_binary: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub wine_prefix: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pre_launch_hooks: Option<Vec<HookCommand>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub post_exit_hooks: Option<Vec<HookCommand>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub env: Option<HashMap<String, String>>, // Extra environment for Run.exe and the game
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub restore_after_exit: Option<bool>, // Undo d3dx.ini edits and boot file copies when the game exits
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub launch_profiles: Option<Vec<LaunchProfile>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub last_launch_profile: Option<String>,
+    #[serde(flatten)]
+    pub extra: serde_json::Map<String, serde_json::Value>,
+}
+
+// Named set of overrides on top of ThreeDMigotoConfig, e.g. "Modding", "Frame dump".
+// Unset fields fall back to the base config; env is merged key by key.
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct LaunchProfile {
+    pub name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub target_exe_path: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub launcher_exe_path: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub launch_args: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub use_shell: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub show_error_popup: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub auto_set_analyse_options: Option<bool>,
+    #[serde(default, deserialize_with = "lenient_i32", skip_serializing_if = "Option::is_none")]
+    pub delay: Option<i32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub extra_dll: Option<String>,
+    #[serde(default, deserialize_with = "lenient_i32", skip_serializing_if = "Option::is_none")]
+    pub auto_exit_seconds: Option<i32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub use_upx: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub launch_mode: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub wine_binary: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub wine_prefix: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub env: Option<HashMap<String, String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub restore_after_exit: Option<bool>,
+    #[serde(flatten)]
+    pub extra: serde_json::Map<String, serde_json::Value>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct GameConfig {
+    // Files without one are upgraded by migrations before they get here, so a
+    // missing value can only come from the frontend and means "current"
+    #[serde(default = "current_schema_version")]
+    pub schema_version: u32,
+    #[serde(default)]
+    pub basic: BasicSettings,
+    #[serde(default)]
+    pub three_d_migoto: ThreeDMigotoConfig,
+    #[serde(default)]
+    pub other: serde_json::Value,
+    #[serde(flatten)]
+    pub extra: serde_json::Map<String, serde_json::Value>,
+}
+
+fn current_schema_version() -> u32 {
+    migrations::GAME_CONFIG.current_version()
+}
+
+impl Default for GameConfig {
+    fn default() -> Self {
+        Self {
+            schema_version: current_schema_version(),
+            basic: BasicSettings::default(),
+            three_d_migoto: ThreeDMigotoConfig::default(),
+            other: serde_json::Value::Null,
+            extra: serde_json::Map::new(),
+        }
+    }
+}
+
+impl ThreeDMigotoConfig {
+    pub fn find_profile(&self, name: &str) -> Option<&LaunchProfile> {
+        self.launch_profiles.as_ref()?.iter().find(|p| p.name == name)
+    }
+
+    pub fn with_profile(&self, profile: &LaunchProfile) -> ThreeDMigotoConfig {
+        let mut env = self.env.clone().unwrap_or_default();
+        env.extend(profile.env.clone().unwrap_or_default());
+
+        ThreeDMigotoConfig {
+            target_exe_path: profile.target_exe_path.clone().or(self.target_exe_path.clone()),
+            launcher_exe_path: profile.launcher_exe_path.clone().or(self.launcher_exe_path.clone()),
+            launch_args: profile.launch_args.clone().or(self.launch_args.clone()),
+            use_shell: profile.use_shell.or(self.use_shell),
+            show_error_popup: profile.show_error_popup.or(self.show_error_popup),
+            auto_set_analyse_options: profile.auto_set_analyse_options.or(self.auto_set_analyse_options),
+            delay: profile.delay.or(self.delay),
+            extra_dll: profile.extra_dll.clone().or(self.extra_dll.clone()),
+            auto_exit_seconds: profile.auto_exit_seconds.or(self.auto_exit_seconds),
+            use_upx: profile.use_upx.or(self.use_upx),
+            launch_mode: profile.launch_mode.clone().or(self.launch_mode.clone()),
+            wine_binary: profile.wine_binary.clone().or(self.wine_binary.clone()),
+            wine_prefix: profile.wine_prefix.clone().or(self.wine_prefix.clone()),
+            env: Some(env),
+            restore_after_exit: profile.restore_after_exit.or(self.restore_after_exit),
+            ..self.clone()
+        }
+    }
+
+    // profile_name: None uses the last-used profile, "" the base config.
+    // Returns the effective config and the profile that was applied.
+    pub fn resolve_profile(&self, profile_name: Option<&str>) -> Result<(ThreeDMigotoConfig, Option<String>), String> {
+        let name = match profile_name {
+            Some(name) => name.trim(),
+            None => self.last_launch_profile.as_deref().unwrap_or("").trim(),
+        };
+        if name.is_empty() {
+            return Ok((self.clone(), None));
+        }
+
+        match self.find_profile(name) {
+            Some(profile) => Ok((self.with_profile(profile), Some(name.to_string()))),
+            // A remembered profile that was deleted since is not worth failing over
+            None if profile_name.is_none() => Ok((self.clone(), None)),
+            None => Err(format!("Launch profile not found: {}", name)),
+        }
+    }
+
+    pub fn launch_env(&self) -> Vec<(String, String)> {
+        let mut env: Vec<(String, String)> = self.env.clone().unwrap_or_default().into_iter().collect();
+        env.sort();
+        env
+    }
+}
+
+// Game names are folder names under Games/ and keys in GameIconConfig.json
+pub fn validate_game_name(name: &str) -> Result<(), String> {
+    const FORBIDDEN: [char; 9] = ['/', '\\', ':', '*', '?', '"', '<', '>', '|'];
+    if name.trim().is_empty() || name == "." || name == ".." {
+        return Err("Game name cannot be empty".to_string());
+    }
+    if name.chars().any(|c| FORBIDDEN.contains(&c) || c.is_control()) {
+        return Err(format!("Game name contains an invalid character: {}", name));
+    }
+    if name.ends_with('.') || name.ends_with(' ') || name.starts_with(' ') {
+        return Err(format!("Game name cannot start with a space or end with a space or dot: {}", name));
+    }
+    Ok(())
+}
+
+pub fn game_dir(app: &AppHandle, game_name: &str) -> PathBuf {
+    get_global_games_dir(app).join(game_name)
+}
+
+pub fn config_path(app: &AppHandle, game_name: &str) -> PathBuf {
+    game_dir(app, game_name).join(CONFIG_FILE_NAME)
+}
+
+// The cache folder from the settings already in memory, falling back to settings.json
+pub(crate) fn cache_dir(app: &AppHandle) -> Result<String, String> {
+    if let Some(state) = app.try_state::<Mutex<AppConfig>>() {
+        return Ok(state.lock().unwrap().cache_dir.clone());
+    }
+    AppConfig::load()
+        .map(|c| c.cache_dir)
+        .map_err(|e| format!("Failed to load app config: {}", e))
+}
+
+impl GameConfig {
+    // Upgrades files written by older releases on the way in
+    pub fn load_from(path: &Path) -> Result<Self, String> {
+        let content = recovery::read_lossy(path)?;
+        if let Some(e) = recovery::find_damage::<Self>(&content, Some(&migrations::GAME_CONFIG))? {
+            return recovery::recover(path, &content, &e, Some(&migrations::GAME_CONFIG));
+        }
+        let value = migrations::load_and_migrate(&migrations::GAME_CONFIG, path, &content)?;
+        serde_json::from_value(value).map_err(|e| format!("Failed to parse config: {}", e))
+    }
+
+    // Errors if the game has no Config.json
+    pub fn load(app: &AppHandle, game_name: &str) -> Result<Self, String> {
+        let path = config_path(app, game_name);
+        if !path.exists() {
+            return Err(format!("Config file not found: {:?}", path));
+        }
+        Self::load_from(&path)
+    }
+
+    pub fn load_or_default(app: &AppHandle, game_name: &str) -> Result<Self, String> {
+        let path = config_path(app, game_name);
+        if path.exists() {
+            Self::load_from(&path)
+        } else {
+            Ok(Self::default())
+        }
+    }
+
+    pub fn save_to(&self, path: &Path) -> Result<(), String> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).map_err(|e| format!("Failed to create config directory: {}", e))?;
+        }
+        let content = serde_json::to_string_pretty(self).map_err(|e| format!("Failed to serialize config: {}", e))?;
+        atomic_file::write(path, content).map_err(|e| format!("Failed to write config: {}", e))
+    }
+
+    pub fn save(&self, app: &AppHandle, game_name: &str) -> Result<(), String> {
+        self.save_to(&config_path(app, game_name))
+    }
+
+    // Load, change and save with the file locked, so two commands touching
+    // different fields do not overwrite each other. A missing file starts from
+    // the defaults.
+    pub fn update<T>(app: &AppHandle, game_name: &str, change: impl FnOnce(&mut Self) -> T) -> Result<T, String> {
+        let path = config_path(app, game_name);
+        let _lock = atomic_file::lock(&path);
+        let mut config = if path.exists() { Self::load_from(&path)? } else { Self::default() };
+        let result = change(&mut config);
+        config.save_to(&path)?;
+        Ok(result)
+    }
+
+    // 3Dmigoto folder: installDir when set, otherwise <cache dir>/3Dmigoto/<game>
+    pub fn migoto_dir(&self, app: &AppHandle, game_name: &str) -> Result<PathBuf, String> {
+        migoto_dir_for(app, game_name, &self.three_d_migoto)
+    }
+}
+
+// Same as GameConfig::migoto_dir, for an already resolved launch profile
+pub fn migoto_dir_for(app: &AppHandle, game_name: &str, config: &ThreeDMigotoConfig) -> Result<PathBuf, String> {
+    resolve_migoto_dir(config, &cache_dir(app)?, game_name)
+}
+
+pub fn resolve_migoto_dir(config: &ThreeDMigotoConfig, cache_dir: &str, game_name: &str) -> Result<PathBuf, String> {
+    if let Some(dir) = config.install_dir.as_deref().filter(|p| !p.trim().is_empty()) {
+        return Ok(PathBuf::from(dir));
+    }
+    if cache_dir.trim().is_empty() {
+        return Err("3Dmigoto Path not set and Cache Dir not set.".into());
+    }
+    Ok(PathBuf::from(cache_dir).join("3Dmigoto").join(game_name))
+}
+
+// Shorthand for commands that only need the 3Dmigoto folder
+pub fn get_game_install_dir(app: &AppHandle, game_name: &str) -> Result<PathBuf, String> {
+    GameConfig::load(app, game_name)?.migoto_dir(app, game_name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn unknown_keys_survive_a_round_trip() {
+        let json = r#"{
+            "basic": { "gamePreset": "ZZMI", "backgroundType": "Video", "accent": "red" },
+            "threeDMigoto": { "installDir": "D:/3Dmigoto", "delay": "", "autoExitSeconds": "7", "futureFlag": true },
+            "other": { "note": 1 },
+            "schemaNote": "kept"
+        }"#;
+        let config: GameConfig = serde_json::from_str(json).unwrap();
+        assert_eq!(config.basic.game_preset, "ZZMI");
+        assert_eq!(config.three_d_migoto.delay, None);
+        assert_eq!(config.three_d_migoto.auto_exit_seconds, Some(7));
+        assert_eq!(config.three_d_migoto.extra.get("futureFlag"), Some(&serde_json::json!(true)));
+        assert_eq!(config.extra.get("schemaNote"), Some(&serde_json::json!("kept")));
+
+        let saved = serde_json::to_value(&config).unwrap();
+        assert_eq!(saved["threeDMigoto"]["futureFlag"], serde_json::json!(true));
+        assert_eq!(saved["threeDMigoto"]["installDir"], serde_json::json!("D:/3Dmigoto"));
+        assert!(saved["threeDMigoto"].get("targetExePath").is_none());
+        assert_eq!(saved["schemaNote"], serde_json::json!("kept"));
+    }
+
+    #[test]
+    fn bad_numbers_are_rejected() {
+        assert!(serde_json::from_str::<ThreeDMigotoConfig>(r#"{ "delay": "soon" }"#).is_err());
+        assert!(serde_json::from_str::<ThreeDMigotoConfig>(r#"{ "delay": [1] }"#).is_err());
+    }
+
+    #[test]
+    fn migoto_dir_prefers_install_dir_then_cache() {
+        let mut config = ThreeDMigotoConfig::default();
+        assert!(resolve_migoto_dir(&config, "", "Genshin").is_err());
+        assert_eq!(
+            resolve_migoto_dir(&config, "C:/Cache", "Genshin").unwrap(),
+            PathBuf::from("C:/Cache").join("3Dmigoto").join("Genshin")
+        );
+
+        config.install_dir = Some("  ".to_string());
+        assert_eq!(
+            resolve_migoto_dir(&config, "C:/Cache", "Genshin").unwrap(),
+            PathBuf::from("C:/Cache").join("3Dmigoto").join("Genshin")
+        );
+
+        config.install_dir = Some("D:/GIMI".to_string());
+        assert_eq!(resolve_migoto_dir(&config, "C:/Cache", "Genshin").unwrap(), PathBuf::from("D:/GIMI"));
+    }
+}
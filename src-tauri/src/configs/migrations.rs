@@ -0,0 +1,316 @@
+use crate::utils::atomic_file;
+use serde_json::{Map, Value};
+use std::path::{Path, PathBuf};
+
+// Upgrades settings.json and Config.json from the shape older releases wrote.
+// Each file stores its schemaVersion (missing means 0, i.e. written before
+// versioning). steps[n] turns version n into n + 1; the current version is
+// steps.len(). Migrations work on raw JSON so they run before the typed model
+// gets a chance to reject or default anything.
+
+pub const SCHEMA_VERSION_KEY: &str = "schemaVersion";
+
+type Migration = fn(&mut Map<String, Value>);
+
+pub struct Schema {
+    pub name: &'static str,
+    steps: &'static [Migration],
+}
+
+impl Schema {
+    pub const fn current_version(&self) -> u32 {
+        self.steps.len() as u32
+    }
+}
+
+pub const APP_CONFIG: Schema = Schema {
+    name: "settings.json",
+    steps: &[app_config_v0_to_v1],
+};
+
+pub const GAME_CONFIG: Schema = Schema {
+    name: "Config.json",
+    steps: &[game_config_v0_to_v1],
+};
+
+pub fn version_of(value: &Value) -> u32 {
+    value
+        .get(SCHEMA_VERSION_KEY)
+        .and_then(|v| v.as_u64())
+        .map(|v| v as u32)
+        .unwrap_or(0)
+}
+
+// Runs every step from the file's version up to the current one. Returns the
+// version the value started at, or None if there was nothing to do. Files from
+// a newer release are left alone.
+pub fn migrate(schema: &Schema, value: &mut Value) -> Result<Option<u32>, String> {
+    let from = version_of(value);
+    let current = schema.current_version();
+    if from >= current {
+        if from > current {
+            println!(
+                "[Migrations] {} has schema version {}, newer than this build ({}); loading as is",
+                schema.name, from, current
+            );
+        }
+        return Ok(None);
+    }
+
+    let obj = value
+        .as_object_mut()
+        .ok_or_else(|| format!("Failed to migrate {}: expected a JSON object", schema.name))?;
+    for (version, step) in schema.steps.iter().enumerate().skip(from as usize) {
+        step(obj);
+        obj.insert(SCHEMA_VERSION_KEY.to_string(), Value::from(version as u32 + 1));
+    }
+    println!("[Migrations] Upgraded {} from version {} to {}", schema.name, from, current);
+    Ok(Some(from))
+}
+
+// <name>.v<version>.bak next to the original
+pub fn backup_path(path: &Path, version: u32) -> PathBuf {
+    let name = path.file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_default();
+    path.with_file_name(format!("{}.v{}.bak", name, version))
+}
+
+// Parses a config file and upgrades it in place when it is out of date. The
+// original is copied aside first; an existing backup of the same version is
+// kept, since it is the older of the two.
+pub fn load_and_migrate(schema: &Schema, path: &Path, content: &str) -> Result<Value, String> {
+    let mut value: Value = serde_json::from_str(content.trim_start_matches('\u{feff}'))
+        .map_err(|e| format!("Failed to parse {}: {}", schema.name, e))?;
+
+    if let Some(from) = migrate(schema, &mut value)? {
+        let backup = backup_path(path, from);
+        if !backup.exists() {
+            // The only copy of the original, so it must not be left half written either
+            atomic_file::write(&backup, content).map_err(|e| format!("Failed to back up {}: {}", schema.name, e))?;
+        }
+        let migrated = serde_json::to_string_pretty(&value)
+            .map_err(|e| format!("Failed to serialize {}: {}", schema.name, e))?;
+        atomic_file::write(path, migrated)
+            .map_err(|e| format!("Failed to write migrated {}: {}", schema.name, e))?;
+    }
+    Ok(value)
+}
+
+// serde's defaults cover a missing key but not an explicit null, which older
+// releases wrote for empty fields
+fn drop_nulls(obj: &mut Map<String, Value>) {
+    obj.retain(|_, v| !v.is_null());
+}
+
+fn capitalize_bg_type(value: &mut Value) {
+    let fixed = match value.as_str().map(|s| s.to_ascii_lowercase()) {
+        Some(s) if s == "image" => "Image",
+        Some(s) if s == "video" => "Video",
+        _ => return,
+    };
+    *value = Value::from(fixed);
+}
+
+// Numbers that were saved from text inputs as strings
+fn numbers_from_strings(obj: &mut Map<String, Value>, keys: &[&str]) {
+    for key in keys {
+        let Some(Value::String(s)) = obj.get(*key) else { continue };
+        let parsed = s.trim().parse::<f64>().ok().and_then(|n| {
+            if n.fract() == 0.0 {
+                Some(Value::from(n as i64))
+            } else {
+                serde_json::Number::from_f64(n).map(Value::Number)
+            }
+        });
+        match parsed {
+            Some(n) => {
+                obj.insert(key.to_string(), n);
+            }
+            // Empty or unreadable: let the default apply
+            None => {
+                obj.remove(*key);
+            }
+        }
+    }
+}
+
+// Before versioning. The layout is unchanged; values the old frontend never
+// wrote but hand-edited files may hold (lowercase bgType, nulls, numbers as
+// strings) are normalised instead of failing the whole file.
+fn app_config_v0_to_v1(obj: &mut Map<String, Value>) {
+    drop_nulls(obj);
+    if let Some(bg_type) = obj.get_mut("bgType") {
+        capitalize_bg_type(bg_type);
+    }
+    numbers_from_strings(
+        obj,
+        &["sidebarOpacity", "sidebarBlur", "contentOpacity", "contentBlur", "windowWidth", "windowHeight"],
+    );
+}
+
+// Flat PascalCase keys of the older config layout (still what resources/Games
+// ships) and where they live now. Unmapped keys such as WorkSpace stay as they are.
+const LEGACY_GAME_KEYS: [(&str, &str, &str); 9] = [
+    ("GamePreset", "basic", "gamePreset"),
+    ("3DmigotoPath", "threeDMigoto", "installDir"),
+    ("TargetPath", "threeDMigoto", "targetExePath"),
+    ("LaunchPath", "threeDMigoto", "launcherExePath"),
+    ("LaunchArgs", "threeDMigoto", "launchArgs"),
+    ("RunWithShell", "threeDMigoto", "useShell"),
+    ("AutoSetAnalyseOptions", "threeDMigoto", "autoSetAnalyseOptions"),
+    ("DllInitializationDelay", "threeDMigoto", "delay"),
+    ("Delay", "threeDMigoto", "autoExitSeconds"),
+];
+
+fn move_legacy_keys(obj: &mut Map<String, Value>) {
+    for (legacy, section, key) in LEGACY_GAME_KEYS {
+        let Some(value) = obj.remove(legacy) else { continue };
+        if value.as_str().is_some_and(|s| s.is_empty()) {
+            continue;
+        }
+        let section = obj.entry(section).or_insert_with(|| Value::Object(Map::new()));
+        if section.is_null() {
+            *section = Value::Object(Map::new());
+        }
+        if let Some(section) = section.as_object_mut() {
+            section.entry(key).or_insert(value);
+        }
+    }
+}
+
+// Before versioning: the flat legacy layout, threeDMigoto and other untyped
+// and saved as null until first edited, and number fields that an emptied
+// input saved as ""
+fn game_config_v0_to_v1(obj: &mut Map<String, Value>) {
+    move_legacy_keys(obj);
+    drop_nulls(obj);
+
+    let basic = obj.entry("basic").or_insert_with(|| Value::Object(Map::new()));
+    if let Some(basic) = basic.as_object_mut() {
+        drop_nulls(basic);
+        basic.entry("gamePreset").or_insert_with(|| Value::from("GIMI"));
+        if let Some(bg_type) = basic.get_mut("backgroundType") {
+            capitalize_bg_type(bg_type);
+        }
+    }
+
+    if let Some(migoto) = obj.get_mut("threeDMigoto").and_then(|v| v.as_object_mut()) {
+        drop_nulls(migoto);
+        numbers_from_strings(migoto, &["delay", "autoExitSeconds"]);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::game_scanner::BGType;
+    use crate::configs::app_config::AppConfig;
+    use crate::configs::game_config::GameConfig;
+    use std::fs;
+
+    // Files as written by releases before schemaVersion existed
+    const SETTINGS_V0: &str = include_str!("../../tests/fixtures/settings.v0.json");
+    const CONFIG_V0: &str = include_str!("../../tests/fixtures/Config.v0.json");
+    const CONFIG_V0_UNTOUCHED: &str = include_str!("../../tests/fixtures/Config.v0-untouched.json");
+    const CONFIG_V0_LEGACY: &str = include_str!("../../tests/fixtures/Config.v0-legacy.json");
+
+    fn temp_file(name: &str, content: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("ssmt4-migrations-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join(name);
+        fs::write(&path, content).unwrap();
+        path
+    }
+
+    #[test]
+    fn old_settings_fixture_upgrades_and_parses() {
+        let path = temp_file("settings.json", SETTINGS_V0);
+        let value = load_and_migrate(&APP_CONFIG, &path, SETTINGS_V0).unwrap();
+        assert_eq!(version_of(&value), APP_CONFIG.current_version());
+
+        let config: AppConfig = serde_json::from_value(value).unwrap();
+        assert_eq!(config.cache_dir, "D:/SSMT4Cache");
+        assert_eq!(config.github_token, "");
+        assert_eq!(config.sidebar_opacity, 0.5);
+        assert_eq!(config.bg_type, BGType::Image);
+        assert!(config.show_mods);
+
+        // Original kept, file on disk rewritten
+        assert_eq!(fs::read_to_string(backup_path(&path, 0)).unwrap(), SETTINGS_V0);
+        let on_disk: Value = serde_json::from_str(&fs::read_to_string(&path).unwrap()).unwrap();
+        assert_eq!(version_of(&on_disk), APP_CONFIG.current_version());
+        let _ = fs::remove_dir_all(path.parent().unwrap());
+    }
+
+    #[test]
+    fn old_game_config_fixtures_upgrade_and_parse() {
+        for fixture in [CONFIG_V0, CONFIG_V0_UNTOUCHED] {
+            let mut value: Value = serde_json::from_str(fixture).unwrap();
+            assert_eq!(migrate(&GAME_CONFIG, &mut value).unwrap(), Some(0));
+            let config: GameConfig = serde_json::from_value(value).unwrap();
+            assert_eq!(config.schema_version, GAME_CONFIG.current_version());
+        }
+
+        let config: GameConfig = {
+            let mut value: Value = serde_json::from_str(CONFIG_V0).unwrap();
+            migrate(&GAME_CONFIG, &mut value).unwrap();
+            serde_json::from_value(value).unwrap()
+        };
+        assert_eq!(config.basic.game_preset, "SRMI");
+        assert_eq!(config.three_d_migoto.delay, Some(150));
+        assert_eq!(config.three_d_migoto.auto_exit_seconds, None);
+        assert_eq!(config.three_d_migoto.install_dir.as_deref(), Some("D:/3Dmigoto/SRMI"));
+    }
+
+    #[test]
+    fn legacy_layout_keys_move_into_sections() {
+        let mut value: Value = serde_json::from_str(CONFIG_V0_LEGACY).unwrap();
+        migrate(&GAME_CONFIG, &mut value).unwrap();
+        let config: GameConfig = serde_json::from_value(value).unwrap();
+
+        assert_eq!(config.basic.game_preset, "HIMI");
+        assert_eq!(config.three_d_migoto.target_exe_path.as_deref(), Some("E:/Honkai Impact 3rd/Games/BH3.exe"));
+        assert_eq!(config.three_d_migoto.install_dir, None);
+        assert_eq!(config.three_d_migoto.use_shell, Some(false));
+        assert_eq!(config.three_d_migoto.delay, Some(100));
+        assert_eq!(config.three_d_migoto.auto_exit_seconds, Some(5));
+        assert!(config.extra.contains_key("WorkSpace"));
+        assert!(!config.extra.contains_key("GamePreset"));
+    }
+
+    #[test]
+    fn current_and_newer_files_are_left_alone() {
+        let current = format!(r#"{{ "schemaVersion": {}, "basic": {{ "gamePreset": "GIMI" }} }}"#, GAME_CONFIG.current_version());
+        let path = temp_file("Config.json", &current);
+        load_and_migrate(&GAME_CONFIG, &path, &current).unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), current);
+        assert!(!backup_path(&path, 0).exists());
+
+        let mut newer = serde_json::json!({ "schemaVersion": 99, "threeDMigoto": null });
+        assert_eq!(migrate(&GAME_CONFIG, &mut newer).unwrap(), None);
+        assert!(newer["threeDMigoto"].is_null());
+        let _ = fs::remove_dir_all(path.parent().unwrap());
+    }
+
+    #[test]
+    fn steps_run_in_order_from_the_stored_version() {
+        fn first(obj: &mut Map<String, Value>) {
+            obj.insert("log".into(), Value::from("1"));
+        }
+        fn second(obj: &mut Map<String, Value>) {
+            let log = obj.get("log").and_then(|v| v.as_str()).unwrap_or("").to_string();
+            obj.insert("log".into(), Value::from(log + "2"));
+        }
+        let schema = Schema { name: "test", steps: &[first, second] };
+
+        let mut from_zero = serde_json::json!({});
+        assert_eq!(migrate(&schema, &mut from_zero).unwrap(), Some(0));
+        assert_eq!(from_zero, serde_json::json!({ "log": "12", "schemaVersion": 2 }));
+
+        let mut from_one = serde_json::json!({ "schemaVersion": 1, "log": "x" });
+        assert_eq!(migrate(&schema, &mut from_one).unwrap(), Some(1));
+        assert_eq!(from_one["log"], "x2");
+
+        assert!(migrate(&schema, &mut serde_json::json!([1])).is_err());
+    }
+}
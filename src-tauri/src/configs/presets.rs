@@ -0,0 +1,287 @@
+use crate::utils::file_manager::{find_bundled_resource, get_global_games_dir};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, RwLock};
+use tauri::AppHandle;
+
+// Everything the app knows about a supported game lives in resources/Presets.json.
+// A Presets.json next to the Games folder (SSMT4GlobalConfigs/Presets.json)
+// overrides it: presets are matched by id and the keys it sets replace the
+// bundled ones, new ids are added.
+
+pub const PRESETS_FILE: &str = "Presets.json";
+
+// Used when the resource file is missing, e.g. running from a bare target dir
+pub(crate) const BUNDLED_PRESETS: &str = include_str!("../../resources/Presets.json");
+
+pub type D3dxSettings = BTreeMap<String, BTreeMap<String, String>>; // section -> key -> value
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct PresetExecutable {
+    pub name: String,
+    #[serde(default)]
+    pub variant: String, // e.g. "Global" / "China" when the exe name tells them apart
+    // Required tail of the parent folder, for generic names like Client-Win64-Shipping.exe
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub parent_suffix: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct GamePreset {
+    pub id: String,
+    #[serde(default)]
+    pub display_name: String,
+    #[serde(default)]
+    pub package_repo: Option<String>, // GitHub owner/repo of the 3Dmigoto package
+    #[serde(default)]
+    pub background_game_id: Option<String>, // HoYoPlay game_id for background updates
+    #[serde(default)]
+    pub executables: Vec<PresetExecutable>,
+    #[serde(default)]
+    pub d3dx: D3dxSettings, // Written into d3dx.ini on every launch, before the game's own settings
+    #[serde(default)]
+    pub analyse_options: Option<String>, // [Hunting] analyse_options when autoSetAnalyseOptions is on
+    #[serde(default)]
+    pub reserved_hotkeys: Vec<String>, // Keys mods should not bind
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct RegistryFile {
+    #[serde(default)]
+    default_preset: Option<String>,
+    #[serde(default)]
+    analyse_options: Option<String>,
+    #[serde(default)]
+    d3dx: D3dxSettings,
+    #[serde(default)]
+    reserved_hotkeys: Vec<String>,
+    #[serde(default)]
+    presets: Vec<GamePreset>,
+}
+
+#[derive(Debug, Clone)]
+pub struct PresetRegistry {
+    pub default_preset: String,
+    pub presets: Vec<GamePreset>,
+    shared: GamePreset, // Registry-wide defaults, for ids the registry does not know
+}
+
+impl PresetRegistry {
+    pub fn get(&self, id: &str) -> Option<&GamePreset> {
+        self.presets.iter().find(|p| p.id == id)
+    }
+
+    // Unknown ids, e.g. a preset dropped from the user file, still get the
+    // registry-wide d3dx settings
+    pub fn get_or_shared(&self, id: &str) -> GamePreset {
+        self.get(id).cloned().unwrap_or_else(|| GamePreset {
+            id: id.to_string(),
+            display_name: id.to_string(),
+            ..self.shared.clone()
+        })
+    }
+
+    pub fn contains(&self, id: &str) -> bool {
+        self.get(id).is_some()
+    }
+}
+
+// User keys replace bundled ones; presets are merged by id, key by key
+fn merge_files(mut bundled: Value, user: Value) -> Value {
+    let (Some(base), Value::Object(user)) = (bundled.as_object_mut(), user) else { return bundled };
+    for (key, value) in user {
+        if key != "presets" {
+            base.insert(key, value);
+            continue;
+        }
+        let Value::Array(user_presets) = value else { continue };
+        let presets = base.entry("presets").or_insert_with(|| Value::Array(Vec::new()));
+        let Some(presets) = presets.as_array_mut() else { continue };
+        for user_preset in user_presets {
+            let id = user_preset.get("id").cloned();
+            match presets.iter_mut().find(|p| id.is_some() && p.get("id") == id.as_ref()) {
+                Some(Value::Object(existing)) => {
+                    if let Value::Object(fields) = user_preset {
+                        existing.extend(fields);
+                    }
+                }
+                _ => presets.push(user_preset),
+            }
+        }
+    }
+    bundled
+}
+
+// Folds the registry-wide defaults into each preset so callers only look at one
+fn resolve(file: RegistryFile) -> PresetRegistry {
+    let shared = GamePreset {
+        d3dx: file.d3dx.clone(),
+        analyse_options: file.analyse_options.clone(),
+        reserved_hotkeys: file.reserved_hotkeys.clone(),
+        ..Default::default()
+    };
+    let presets = file
+        .presets
+        .into_iter()
+        .map(|mut preset| {
+            if preset.display_name.is_empty() {
+                preset.display_name = preset.id.clone();
+            }
+            let mut d3dx = file.d3dx.clone();
+            for (section, keys) in std::mem::take(&mut preset.d3dx) {
+                d3dx.entry(section).or_default().extend(keys);
+            }
+            preset.d3dx = d3dx;
+            preset.analyse_options = preset.analyse_options.or(file.analyse_options.clone());
+            let mut hotkeys = file.reserved_hotkeys.clone();
+            for key in std::mem::take(&mut preset.reserved_hotkeys) {
+                if !hotkeys.iter().any(|k| k.eq_ignore_ascii_case(&key)) {
+                    hotkeys.push(key);
+                }
+            }
+            preset.reserved_hotkeys = hotkeys;
+            preset
+        })
+        .collect::<Vec<_>>();
+
+    let default_preset = file
+        .default_preset
+        .filter(|id| presets.iter().any(|p| &p.id == id))
+        .or_else(|| presets.first().map(|p| p.id.clone()))
+        .unwrap_or_default();
+    PresetRegistry {
+        default_preset,
+        presets,
+        shared,
+    }
+}
+
+pub fn parse_registry(bundled: &str, user: Option<&str>) -> Result<PresetRegistry, String> {
+    let bundled: Value = serde_json::from_str(bundled).map_err(|e| format!("Failed to parse bundled presets: {}", e))?;
+    let merged = match user {
+        Some(user) => {
+            let user: Value =
+                serde_json::from_str(user).map_err(|e| format!("Failed to parse {}: {}", PRESETS_FILE, e))?;
+            merge_files(bundled, user)
+        }
+        None => bundled,
+    };
+    let file: RegistryFile =
+        serde_json::from_value(merged).map_err(|e| format!("Invalid preset registry: {}", e))?;
+    Ok(resolve(file))
+}
+
+static REGISTRY: RwLock<Option<Arc<PresetRegistry>>> = RwLock::new(None);
+
+pub fn user_presets_path(app: &AppHandle) -> PathBuf {
+    let games_dir = get_global_games_dir(app);
+    games_dir.parent().unwrap_or(&games_dir).join(PRESETS_FILE)
+}
+
+fn read_optional(path: &Path) -> Result<Option<String>, String> {
+    if !path.exists() {
+        return Ok(None);
+    }
+    fs::read_to_string(path)
+        .map(Some)
+        .map_err(|e| format!("Failed to read {:?}: {}", path, e))
+}
+
+// Re-reads both files. Errors from the user file are returned, not papered over.
+pub fn reload(app: &AppHandle) -> Result<Arc<PresetRegistry>, String> {
+    let bundled = find_bundled_resource(app, PRESETS_FILE)
+        .and_then(|path| fs::read_to_string(path).ok())
+        .unwrap_or_else(|| BUNDLED_PRESETS.to_string());
+    let user = read_optional(&user_presets_path(app))?;
+
+    let registry = Arc::new(parse_registry(&bundled, user.as_deref())?);
+    *REGISTRY.write().unwrap() = Some(registry.clone());
+    println!("[Presets] Loaded {} presets", registry.presets.len());
+    Ok(registry)
+}
+
+// Loaded once and shared. A broken user override falls back to the bundled list.
+pub fn registry(app: &AppHandle) -> Arc<PresetRegistry> {
+    if let Some(registry) = REGISTRY.read().unwrap().as_ref() {
+        return registry.clone();
+    }
+    reload(app).unwrap_or_else(|e| {
+        eprintln!("[Presets] {}, using the bundled presets", e);
+        let registry = Arc::new(parse_registry(BUNDLED_PRESETS, None).expect("bundled Presets.json is valid"));
+        *REGISTRY.write().unwrap() = Some(registry.clone());
+        registry
+    })
+}
+
+// For defaults that are built without an AppHandle (BasicSettings::default)
+pub fn default_preset_id() -> String {
+    if let Some(registry) = REGISTRY.read().unwrap().as_ref() {
+        return registry.default_preset.clone();
+    }
+    parse_registry(BUNDLED_PRESETS, None)
+        .map(|r| r.default_preset)
+        .unwrap_or_else(|_| "GIMI".to_string())
+}
+
+#[tauri::command]
+pub fn list_game_presets(app: AppHandle) -> Result<Vec<GamePreset>, String> {
+    Ok(registry(&app).presets.clone())
+}
+
+#[tauri::command]
+pub fn reload_game_presets(app: AppHandle) -> Result<Vec<GamePreset>, String> {
+    Ok(reload(&app)?.presets.clone())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bundled_registry_resolves_shared_defaults() {
+        let registry = parse_registry(BUNDLED_PRESETS, None).unwrap();
+        assert_eq!(registry.default_preset, "GIMI");
+
+        let gimi = registry.get("GIMI").unwrap();
+        assert_eq!(gimi.package_repo.as_deref(), Some("SilentNightSound/GIMI-Package"));
+        assert_eq!(gimi.d3dx["Hunting"]["hunting"], "2");
+        assert_eq!(gimi.d3dx["System"]["dll_initialization_delay"], "100");
+        assert!(gimi.analyse_options.is_some());
+        assert!(gimi.reserved_hotkeys.iter().any(|k| k == "VK_F10"));
+        assert!(registry.get("DIY").is_some_and(|p| p.executables.is_empty()));
+        let wuwa = registry.get("WuWa").unwrap();
+        assert_eq!(wuwa.executables[0].parent_suffix.as_deref(), Some("Client/Binaries/Win64"));
+        assert_eq!(registry.get_or_shared("Unknown").d3dx["Hunting"]["hunting"], "2");
+    }
+
+    #[test]
+    fn user_file_overrides_by_id_and_adds_presets() {
+        let user = r#"{
+            "defaultPreset": "MyGame",
+            "presets": [
+                { "id": "GIMI", "packageRepo": "me/GIMI-Fork", "reservedHotkeys": ["VK_F6"] },
+                { "id": "MyGame", "displayName": "My Game", "executables": [{ "name": "MyGame.exe" }],
+                  "d3dx": { "Hunting": { "hunting": "0" } } }
+            ]
+        }"#;
+        let registry = parse_registry(BUNDLED_PRESETS, Some(user)).unwrap();
+        assert_eq!(registry.default_preset, "MyGame");
+
+        let gimi = registry.get("GIMI").unwrap();
+        assert_eq!(gimi.package_repo.as_deref(), Some("me/GIMI-Fork"));
+        assert_eq!(gimi.background_game_id.as_deref(), Some("1Z8W5NHUQb"));
+        assert!(gimi.reserved_hotkeys.iter().any(|k| k == "VK_F6"));
+
+        let mine = registry.get("MyGame").unwrap();
+        assert_eq!(mine.d3dx["Hunting"]["hunting"], "0");
+        assert_eq!(mine.d3dx["Hunting"]["marking_actions"], "clipboard asm hlsl");
+
+        assert!(parse_registry(BUNDLED_PRESETS, Some("{ not json")).is_err());
+    }
+}
@@ -0,0 +1,326 @@
+use crate::configs::migrations::{self, Schema};
+use crate::utils::atomic_file;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use serde_json::Value;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, OnceLock};
+use tauri::{AppHandle, Emitter};
+
+// What happens when settings.json, Config.json or GameIconConfig.json cannot
+// be loaded: the damaged file is moved aside as <name>.corrupt-<unix time>,
+// whatever still parses is carried over key by key, the result is written back
+// and the UI is told. Before this, the defaults silently replaced the file on
+// the next save.
+
+pub const RECOVERY_EVENT: &str = "config-recovered";
+
+#[derive(Debug, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct RecoveryNotice {
+    pub file: String,
+    pub backup_path: String,
+    pub error: String,
+    pub recovered_keys: Vec<String>,
+    pub dropped_keys: Vec<String>, // Reset to the default; the old values are still in the backup
+}
+
+// settings.json is loaded before there is a window to tell, so notices wait
+// here until the frontend takes them
+static PENDING: Mutex<Vec<RecoveryNotice>> = Mutex::new(Vec::new());
+static APP: OnceLock<AppHandle> = OnceLock::new();
+
+pub fn set_app_handle(app: AppHandle) {
+    let _ = APP.set(app);
+}
+
+fn report(notice: RecoveryNotice) {
+    eprintln!(
+        "[Recovery] {} was damaged ({}), moved to {}. Kept {} key(s), reset {}",
+        notice.file,
+        notice.error,
+        notice.backup_path,
+        notice.recovered_keys.len(),
+        notice.dropped_keys.len()
+    );
+    PENDING.lock().unwrap().push(notice);
+    // Only a nudge; the frontend collects the notices with take_config_recovery_notices
+    if let Some(app) = APP.get() {
+        let _ = app.emit(RECOVERY_EVENT, ());
+    }
+}
+
+#[tauri::command]
+pub fn take_config_recovery_notices() -> Result<Vec<RecoveryNotice>, String> {
+    Ok(std::mem::take(&mut *PENDING.lock().unwrap()))
+}
+
+fn corrupt_backup_path(path: &Path) -> PathBuf {
+    let name = path.file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_default();
+    let now = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0);
+    let mut backup = path.with_file_name(format!("{}.corrupt-{}", name, now));
+    let mut n = 1;
+    while backup.exists() {
+        backup = path.with_file_name(format!("{}.corrupt-{}-{}", name, now, n));
+        n += 1;
+    }
+    backup
+}
+
+// A file cut off mid-write: the longest prefix that becomes valid JSON once
+// the open brackets are closed. Cut points are after "{" / "[", after a
+// closing bracket and before a comma, so only whole members are kept.
+fn repair_truncated(content: &str) -> Option<Value> {
+    let content = content.trim_start_matches('\u{feff}');
+    let mut stack: Vec<char> = Vec::new();
+    let mut cuts: Vec<(usize, Vec<char>)> = Vec::new();
+    let mut in_string = false;
+    let mut escaped = false;
+
+    for (i, c) in content.char_indices() {
+        if in_string {
+            match c {
+                _ if escaped => escaped = false,
+                '\\' => escaped = true,
+                '"' => in_string = false,
+                _ => {}
+            }
+            continue;
+        }
+        match c {
+            '"' => in_string = true,
+            '{' | '[' => {
+                stack.push(c);
+                cuts.push((i + 1, stack.clone()));
+            }
+            '}' | ']' => {
+                stack.pop();
+                cuts.push((i + 1, stack.clone()));
+                // Anything after the top-level object is junk
+                if stack.is_empty() {
+                    break;
+                }
+            }
+            ',' => cuts.push((i, stack.clone())),
+            _ => {}
+        }
+    }
+
+    cuts.iter().rev().find_map(|(end, open)| {
+        let mut candidate = content[..*end].to_string();
+        candidate.extend(open.iter().rev().map(|b| if *b == '{' { '}' } else { ']' }));
+        serde_json::from_str::<Value>(&candidate).ok().filter(Value::is_object)
+    })
+}
+
+fn pointer_key(key: &str) -> String {
+    key.replace('~', "~0").replace('/', "~1")
+}
+
+struct Salvage {
+    recovered: Vec<String>,
+    dropped: Vec<String>,
+}
+
+impl Salvage {
+    // Copies raw's keys into target[pointer] one at a time, keeping each only if
+    // T still deserializes. Objects and lists that fail as a whole are tried
+    // member by member.
+    fn merge<T: DeserializeOwned>(&mut self, target: &mut Value, pointer: &str, label: &str, raw: &serde_json::Map<String, Value>) {
+        for (key, value) in raw {
+            let child_pointer = format!("{}/{}", pointer, pointer_key(key));
+            let child_label = if label.is_empty() { key.clone() } else { format!("{}.{}", label, key) };
+            let previous = target.pointer(&child_pointer).cloned();
+
+            if try_set::<T>(target, pointer, key, value.clone()) {
+                self.recovered.push(child_label);
+                continue;
+            }
+            match (value, previous) {
+                (Value::Object(fields), Some(Value::Object(_))) => {
+                    self.merge::<T>(target, &child_pointer, &child_label, fields);
+                }
+                (Value::Array(items), _) if try_set::<T>(target, pointer, key, Value::Array(Vec::new())) => {
+                    for (i, item) in items.iter().enumerate() {
+                        let list = target.pointer_mut(&child_pointer).and_then(Value::as_array_mut);
+                        list.expect("just set to a list").push(item.clone());
+                        if serde_json::from_value::<T>(target.clone()).is_ok() {
+                            self.recovered.push(format!("{}[{}]", child_label, i));
+                            continue;
+                        }
+                        if let Some(list) = target.pointer_mut(&child_pointer).and_then(Value::as_array_mut) {
+                            list.pop();
+                        }
+                        self.dropped.push(format!("{}[{}]", child_label, i));
+                    }
+                }
+                _ => self.dropped.push(child_label),
+            }
+        }
+    }
+}
+
+// Sets target[pointer][key] = value if T accepts the result, otherwise leaves target unchanged
+fn try_set<T: DeserializeOwned>(target: &mut Value, pointer: &str, key: &str, value: Value) -> bool {
+    let Some(Value::Object(parent)) = target.pointer_mut(pointer) else { return false };
+    let previous = parent.insert(key.to_string(), value);
+    if serde_json::from_value::<T>(target.clone()).is_ok() {
+        return true;
+    }
+    let Some(Value::Object(parent)) = target.pointer_mut(pointer) else { return false };
+    match previous {
+        Some(previous) => parent.insert(key.to_string(), previous),
+        None => parent.remove(key),
+    };
+    false
+}
+
+// Starts from T::default() and takes every key of raw that T accepts
+pub fn salvage<T: Serialize + DeserializeOwned + Default>(raw: &Value) -> (T, Vec<String>, Vec<String>) {
+    let mut target = serde_json::to_value(T::default()).unwrap_or_else(|_| Value::Object(Default::default()));
+    let mut salvage = Salvage {
+        recovered: Vec::new(),
+        dropped: Vec::new(),
+    };
+    if let Some(raw) = raw.as_object() {
+        salvage.merge::<T>(&mut target, "", "", raw);
+    }
+    let value = serde_json::from_value(target).unwrap_or_default();
+    (value, salvage.recovered, salvage.dropped)
+}
+
+// Why content cannot be loaded as T, or None when it can. Migrations run on a
+// copy first, so files from older releases are not mistaken for damaged ones.
+// A file that fails to migrate, or that a newer release wrote, is not damaged
+// and must not be moved aside, so those are errors instead.
+pub fn find_damage<T: DeserializeOwned>(content: &str, schema: Option<&Schema>) -> Result<Option<String>, String> {
+    let mut value = match serde_json::from_str::<Value>(content.trim_start_matches('\u{feff}')) {
+        Ok(value) if value.is_object() => value,
+        Ok(_) => return Ok(Some("expected a JSON object".to_string())),
+        Err(e) => return Ok(Some(e.to_string())),
+    };
+    if let Some(schema) = schema {
+        migrations::migrate(schema, &mut value)?;
+    }
+    match serde_json::from_value::<T>(value.clone()) {
+        Ok(_) => Ok(None),
+        Err(e) => match schema {
+            Some(schema) if migrations::version_of(&value) > schema.current_version() => Err(format!(
+                "{} was written by a newer version (schema {}): {}",
+                schema.name,
+                migrations::version_of(&value),
+                e
+            )),
+            _ => Ok(Some(e.to_string())),
+        },
+    }
+}
+
+// Read as lossy UTF-8, since invalid bytes are one way a file gets damaged
+pub fn read_lossy(path: &Path) -> Result<String, String> {
+    let bytes = fs::read(path).map_err(|e| format!("Failed to read {:?}: {}", path, e))?;
+    Ok(String::from_utf8_lossy(&bytes).into_owned())
+}
+
+// content: what was read from path; error: why loading it failed.
+// schema: migrations to run on the salvaged JSON, for files that have them.
+pub fn recover<T: Serialize + DeserializeOwned + Default>(
+    path: &Path,
+    content: &str,
+    error: &str,
+    schema: Option<&Schema>,
+) -> Result<T, String> {
+    let _lock = atomic_file::lock(path);
+    let backup = corrupt_backup_path(path);
+    fs::rename(path, &backup).map_err(|e| format!("Failed to move damaged {:?} aside: {}", path, e))?;
+
+    let mut raw = serde_json::from_str::<Value>(content.trim_start_matches('\u{feff}'))
+        .ok()
+        .or_else(|| repair_truncated(content))
+        .unwrap_or(Value::Null);
+    if let Some(schema) = schema {
+        if raw.is_object() {
+            if let Err(e) = migrations::migrate(schema, &mut raw) {
+                eprintln!("[Recovery] {}", e);
+            }
+        }
+    }
+
+    let (value, recovered_keys, dropped_keys) = salvage::<T>(&raw);
+    let content = serde_json::to_string_pretty(&value).map_err(|e| format!("Failed to serialize {:?}: {}", path, e))?;
+    atomic_file::write(path, content).map_err(|e| format!("Failed to write recovered {:?}: {}", path, e))?;
+
+    report(RecoveryNotice {
+        file: path.to_string_lossy().to_string(),
+        backup_path: backup.to_string_lossy().to_string(),
+        error: error.to_string(),
+        recovered_keys,
+        dropped_keys,
+    });
+    Ok(value)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::configs::app_config::AppConfig;
+    use crate::configs::game_config::GameConfig;
+
+    #[test]
+    fn truncated_file_keeps_whole_members() {
+        let content = "{\n  \"cacheDir\": \"D:\\\\Cache\",\n  \"githubToken\": \"abc\",\n  \"sidebarBl";
+        let repaired = repair_truncated(content).unwrap();
+        assert_eq!(repaired["githubToken"], "abc");
+        assert!(repaired.get("sidebarBlur").is_none());
+
+        let nested = r#"{ "basic": { "gamePreset": "SRMI" }, "threeDMigoto": { "delay": 200, "launchProfiles": [{ "name": "A" }, { "na"#;
+        let repaired = repair_truncated(nested).unwrap();
+        assert_eq!(repaired["threeDMigoto"]["launchProfiles"][0]["name"], "A");
+        assert_eq!(repaired["threeDMigoto"]["delay"], 200);
+    }
+
+    #[test]
+    fn files_from_a_newer_release_are_not_damage() {
+        let newer = r#"{ "schemaVersion": 99, "sidebarBlur": { "px": 20 } }"#;
+        assert!(find_damage::<AppConfig>(newer, Some(&migrations::APP_CONFIG)).is_err());
+        let newer_but_readable = r#"{ "schemaVersion": 99, "sidebarBlur": 20 }"#;
+        assert_eq!(find_damage::<AppConfig>(newer_but_readable, Some(&migrations::APP_CONFIG)), Ok(None));
+        let damaged = r#"{ "schemaVersion": 1, "sidebarBlur": "very" }"#;
+        assert!(find_damage::<AppConfig>(damaged, Some(&migrations::APP_CONFIG)).unwrap().is_some());
+        assert!(find_damage::<AppConfig>("{ \"cacheDir\": ", Some(&migrations::APP_CONFIG)).unwrap().is_some());
+    }
+
+    #[test]
+    fn salvage_keeps_valid_keys_and_resets_bad_ones() {
+        let raw = serde_json::json!({
+            "cacheDir": "D:/Cache",
+            "githubToken": "secret",
+            "sidebarBlur": "very",
+            "showMods": false
+        });
+        let (config, recovered, dropped) = salvage::<AppConfig>(&raw);
+        assert_eq!(config.cache_dir, "D:/Cache");
+        assert_eq!(config.github_token, "secret");
+        assert_eq!(config.sidebar_blur, AppConfig::default().sidebar_blur);
+        assert!(!config.show_mods);
+        assert_eq!(dropped, ["sidebarBlur"]);
+        assert_eq!(recovered.len(), 3);
+
+        let raw = serde_json::json!({
+            "basic": { "gamePreset": "SRMI", "backgroundType": 7 },
+            "threeDMigoto": { "targetExePath": "C:/Game/StarRail.exe", "delay": [], "launchProfiles": [{ "name": "A" }, 5] }
+        });
+        let (config, _, dropped) = salvage::<GameConfig>(&raw);
+        assert_eq!(config.basic.game_preset, "SRMI");
+        assert_eq!(config.three_d_migoto.target_exe_path.as_deref(), Some("C:/Game/StarRail.exe"));
+        assert_eq!(config.three_d_migoto.launch_profiles.map(|p| p.len()), Some(1));
+        assert_eq!(
+            dropped,
+            ["basic.backgroundType", "threeDMigoto.delay", "threeDMigoto.launchProfiles[1]"]
+        );
+    }
+}
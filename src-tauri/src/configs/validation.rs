@@ -0,0 +1,379 @@
+use crate::configs::game_config::{GameConfig, LaunchProfile, ThreeDMigotoConfig};
+use crate::configs::presets::PresetRegistry;
+use crate::utils::hooks::HookCommand;
+use crate::utils::launcher;
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::path::Path;
+
+// Checks a game config before it is saved, so a bad path or number shows up
+// next to the field instead of as a failed launch. Field paths use the JSON
+// names, e.g. "threeDMigoto.launchProfiles[1].delay".
+
+// [System] dll_initialization_delay, in milliseconds
+const MAX_DELAY_MS: i64 = 60_000;
+// How long Run.exe waits before closing itself
+const MAX_AUTO_EXIT_SECONDS: i64 = 600;
+
+#[derive(Debug, Serialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct FieldIssue {
+    pub field: String,
+    pub message: String,
+}
+
+#[derive(Default)]
+struct Issues(Vec<FieldIssue>);
+
+impl Issues {
+    fn push(&mut self, field: impl Into<String>, message: impl Into<String>) {
+        self.0.push(FieldIssue {
+            field: field.into(),
+            message: message.into(),
+        });
+    }
+}
+
+#[derive(Clone, Copy)]
+enum Kind {
+    Text,
+    Flag,
+    Number,
+    TextMap,
+    List,
+}
+
+// Fields LaunchProfile shares with ThreeDMigotoConfig
+const PROFILE_FIELDS: &[(&str, Kind)] = &[
+    ("targetExePath", Kind::Text),
+    ("launcherExePath", Kind::Text),
+    ("launchArgs", Kind::Text),
+    ("useShell", Kind::Flag),
+    ("showErrorPopup", Kind::Flag),
+    ("autoSetAnalyseOptions", Kind::Flag),
+    ("delay", Kind::Number),
+    ("extraDll", Kind::Text),
+    ("autoExitSeconds", Kind::Number),
+    ("useUpx", Kind::Flag),
+    ("launchMode", Kind::Text),
+    ("wineBinary", Kind::Text),
+    ("winePrefix", Kind::Text),
+    ("env", Kind::TextMap),
+    ("restoreAfterExit", Kind::Flag),
+];
+
+const MIGOTO_ONLY_FIELDS: &[(&str, Kind)] = &[
+    ("installDir", Kind::Text),
+    ("preLaunchHooks", Kind::List),
+    ("postExitHooks", Kind::List),
+    ("launchProfiles", Kind::List),
+    ("lastLaunchProfile", Kind::Text),
+];
+
+fn type_error(kind: Kind, value: &Value) -> Option<&'static str> {
+    let ok = match (kind, value) {
+        (_, Value::Null) => true,
+        (Kind::Text, Value::String(_)) | (Kind::Flag, Value::Bool(_)) | (Kind::List, Value::Array(_)) => true,
+        // The settings form sends "" for a cleared number input
+        (Kind::Number, Value::Number(_)) => true,
+        (Kind::Number, Value::String(s)) => s.trim().is_empty() || s.trim().parse::<i64>().is_ok(),
+        (Kind::TextMap, Value::Object(map)) => map.values().all(|v| v.is_string()),
+        _ => false,
+    };
+    if ok {
+        return None;
+    }
+    Some(match kind {
+        Kind::Text => "Expected text",
+        Kind::Flag => "Expected true or false",
+        Kind::Number => "Expected a whole number",
+        Kind::TextMap => "Expected an object of text values",
+        Kind::List => "Expected a list",
+    })
+}
+
+fn check_types(object: &serde_json::Map<String, Value>, prefix: &str, fields: &[(&str, Kind)], issues: &mut Issues) {
+    for (name, kind) in fields {
+        if let Some(message) = object.get(*name).and_then(|v| type_error(*kind, v)) {
+            issues.push(format!("{}.{}", prefix, name), message);
+        }
+    }
+}
+
+// Wrong JSON types, reported per field since serde stops at the first one
+fn check_raw(raw: &Value, issues: &mut Issues) {
+    let Some(root) = raw.as_object() else {
+        issues.push("", "Expected a JSON object");
+        return;
+    };
+
+    if let Some(basic) = root.get("basic") {
+        match basic.as_object() {
+            Some(basic) => {
+                check_types(basic, "basic", &[("gamePreset", Kind::Text)], issues);
+                if let Some(bg) = basic.get("backgroundType").filter(|v| !v.is_null()) {
+                    if !matches!(bg.as_str(), Some("Image" | "Video")) {
+                        issues.push("basic.backgroundType", "Expected \"Image\" or \"Video\"");
+                    }
+                }
+            }
+            None => issues.push("basic", "Expected an object"),
+        }
+    }
+
+    let Some(migoto) = root.get("threeDMigoto").filter(|v| !v.is_null()) else { return };
+    let Some(migoto) = migoto.as_object() else {
+        issues.push("threeDMigoto", "Expected an object");
+        return;
+    };
+    check_types(migoto, "threeDMigoto", PROFILE_FIELDS, issues);
+    check_types(migoto, "threeDMigoto", MIGOTO_ONLY_FIELDS, issues);
+
+    if let Some(Value::Array(profiles)) = migoto.get("launchProfiles") {
+        for (i, profile) in profiles.iter().enumerate() {
+            let prefix = format!("threeDMigoto.launchProfiles[{}]", i);
+            match profile.as_object() {
+                Some(profile) => {
+                    check_types(profile, &prefix, &[("name", Kind::Text)], issues);
+                    check_types(profile, &prefix, PROFILE_FIELDS, issues);
+                }
+                None => issues.push(prefix, "Expected an object"),
+            }
+        }
+    }
+    for hooks in ["preLaunchHooks", "postExitHooks"] {
+        let Some(Value::Array(list)) = migoto.get(hooks) else { continue };
+        for (i, hook) in list.iter().enumerate() {
+            if let Err(e) = serde_json::from_value::<HookCommand>(hook.clone()) {
+                issues.push(format!("threeDMigoto.{}[{}]", hooks, i), format!("Invalid hook: {}", e));
+            }
+        }
+    }
+}
+
+fn is_set(value: &Option<String>) -> Option<&str> {
+    value.as_deref().map(str::trim).filter(|v| !v.is_empty())
+}
+
+fn has_extension(path: &Path, extension: &str) -> bool {
+    path.extension().is_some_and(|e| e.eq_ignore_ascii_case(extension))
+}
+
+// extension: required file extension, e.g. "exe"
+fn check_file(field: &str, value: &Option<String>, extension: Option<&str>, issues: &mut Issues) {
+    let Some(value) = is_set(value) else { return };
+    let path = Path::new(value);
+    if !path.exists() {
+        issues.push(field, format!("File does not exist: {}", value));
+    } else if path.is_dir() {
+        issues.push(field, format!("Expected a file, this is a folder: {}", value));
+    } else if let Some(extension) = extension.filter(|e| !has_extension(path, e)) {
+        issues.push(field, format!("Expected a .{} file: {}", extension, value));
+    }
+}
+
+fn check_range(field: &str, value: Option<i32>, max: i64, issues: &mut Issues) {
+    if let Some(value) = value.map(i64::from).filter(|v| *v < 0 || *v > max) {
+        issues.push(field, format!("{} is out of range, expected 0 to {}", value, max));
+    }
+}
+
+fn check_env(field: &str, env: &Option<HashMap<String, String>>, issues: &mut Issues) {
+    for key in env.iter().flat_map(|env| env.keys()) {
+        if key.trim().is_empty() || key.contains('=') {
+            issues.push(field, format!("Invalid environment variable name: \"{}\"", key));
+        }
+    }
+}
+
+// The fields a launch profile can override; prefix is "threeDMigoto" or the profile's path
+fn check_launch_fields(prefix: &str, profile: &LaunchProfile, issues: &mut Issues) {
+    let field = |name: &str| format!("{}.{}", prefix, name);
+    check_file(&field("targetExePath"), &profile.target_exe_path, Some("exe"), issues);
+    check_file(&field("launcherExePath"), &profile.launcher_exe_path, None, issues);
+    check_file(&field("extraDll"), &profile.extra_dll, Some("dll"), issues);
+    check_range(&field("delay"), profile.delay, MAX_DELAY_MS, issues);
+    check_range(&field("autoExitSeconds"), profile.auto_exit_seconds, MAX_AUTO_EXIT_SECONDS, issues);
+    check_env(&field("env"), &profile.env, issues);
+
+    if let Err(e) = launcher::split_args(profile.launch_args.as_deref().unwrap_or_default()) {
+        issues.push(field("launchArgs"), e);
+    }
+    if profile.launch_mode.is_some() || profile.wine_binary.is_some() {
+        if let Err(e) = launcher::create_launcher(
+            profile.launch_mode.as_deref(),
+            profile.wine_binary.as_deref(),
+            profile.wine_prefix.as_deref(),
+        ) {
+            issues.push(field("launchMode"), e);
+        }
+    }
+    // A bare "wine" is looked up on PATH
+    if let Some(binary) = is_set(&profile.wine_binary).filter(|b| b.contains(['/', '\\'])) {
+        if !Path::new(binary).is_file() {
+            issues.push(field("wineBinary"), format!("File does not exist: {}", binary));
+        }
+    }
+    if let Some(prefix_dir) = is_set(&profile.wine_prefix) {
+        if !Path::new(prefix_dir).is_dir() {
+            issues.push(field("winePrefix"), format!("Folder does not exist: {}", prefix_dir));
+        }
+    }
+}
+
+// The base config seen as a profile, so both go through check_launch_fields
+fn as_profile(migoto: &ThreeDMigotoConfig) -> LaunchProfile {
+    LaunchProfile {
+        name: String::new(),
+        target_exe_path: migoto.target_exe_path.clone(),
+        launcher_exe_path: migoto.launcher_exe_path.clone(),
+        launch_args: migoto.launch_args.clone(),
+        use_shell: migoto.use_shell,
+        show_error_popup: migoto.show_error_popup,
+        auto_set_analyse_options: migoto.auto_set_analyse_options,
+        delay: migoto.delay,
+        extra_dll: migoto.extra_dll.clone(),
+        auto_exit_seconds: migoto.auto_exit_seconds,
+        use_upx: migoto.use_upx,
+        launch_mode: migoto.launch_mode.clone(),
+        wine_binary: migoto.wine_binary.clone(),
+        wine_prefix: migoto.wine_prefix.clone(),
+        env: migoto.env.clone(),
+        restore_after_exit: migoto.restore_after_exit,
+        extra: serde_json::Map::new(),
+    }
+}
+
+// Checks that need the typed config: paths on disk, ranges, the preset
+pub fn validate_config(config: &GameConfig, presets: &PresetRegistry) -> Vec<FieldIssue> {
+    let mut issues = Issues::default();
+
+    let preset = config.basic.game_preset.trim();
+    if preset.is_empty() {
+        issues.push("basic.gamePreset", "Game preset is required");
+    } else if !presets.contains(preset) {
+        issues.push("basic.gamePreset", format!("Unknown game preset: {}", preset));
+    }
+
+    let migoto = &config.three_d_migoto;
+    // Empty means the cache folder, which start_game fills in
+    if let Some(dir) = is_set(&migoto.install_dir) {
+        let path = Path::new(dir);
+        if !path.is_dir() {
+            issues.push("threeDMigoto.installDir", format!("Folder does not exist: {}", dir));
+        } else if !path.join("d3dx.ini").is_file() {
+            issues.push("threeDMigoto.installDir", "No d3dx.ini in this folder, is 3Dmigoto installed here?");
+        }
+    }
+    check_launch_fields("threeDMigoto", &as_profile(migoto), &mut issues);
+
+    let mut names: Vec<&str> = Vec::new();
+    for (i, profile) in migoto.launch_profiles.iter().flatten().enumerate() {
+        let prefix = format!("threeDMigoto.launchProfiles[{}]", i);
+        let name = profile.name.trim();
+        if name.is_empty() {
+            issues.push(format!("{}.name", prefix), "Profile name is required");
+        } else if names.contains(&name) {
+            issues.push(format!("{}.name", prefix), format!("Another profile is already named {}", name));
+        }
+        names.push(name);
+        check_launch_fields(&prefix, profile, &mut issues);
+    }
+
+    for (list, hooks) in [("preLaunchHooks", &migoto.pre_launch_hooks), ("postExitHooks", &migoto.post_exit_hooks)] {
+        for (i, hook) in hooks.iter().flatten().enumerate() {
+            if hook.command.trim().is_empty() {
+                issues.push(format!("threeDMigoto.{}[{}].command", list, i), "Command is required");
+            }
+        }
+    }
+
+    issues.0
+}
+
+// For configs straight from the frontend: JSON type errors first, and only
+// when there are none the checks on the typed config
+pub fn validate_value(raw: &Value, presets: &PresetRegistry) -> Vec<FieldIssue> {
+    let mut issues = Issues::default();
+    check_raw(raw, &mut issues);
+    if !issues.0.is_empty() {
+        return issues.0;
+    }
+    match serde_json::from_value::<GameConfig>(raw.clone()) {
+        Ok(config) => validate_config(&config, presets),
+        Err(e) => {
+            issues.push("", format!("Invalid config: {}", e));
+            issues.0
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::configs::presets::{parse_registry, BUNDLED_PRESETS};
+    use serde_json::json;
+
+    #[test]
+    fn reports_type_errors_per_field() {
+        let registry = parse_registry(BUNDLED_PRESETS, None).unwrap();
+        let raw = json!({
+            "basic": { "gamePreset": 3 },
+            "threeDMigoto": {
+                "delay": "soon",
+                "useShell": "yes",
+                "launchProfiles": [{ "name": "Dump", "autoExitSeconds": [] }]
+            }
+        });
+        let fields: Vec<String> = validate_value(&raw, &registry).into_iter().map(|i| i.field).collect();
+        assert_eq!(
+            fields,
+            [
+                "basic.gamePreset",
+                "threeDMigoto.useShell",
+                "threeDMigoto.delay",
+                "threeDMigoto.launchProfiles[0].autoExitSeconds"
+            ]
+        );
+    }
+
+    #[test]
+    fn reports_paths_ranges_and_unknown_preset() {
+        let registry = parse_registry(BUNDLED_PRESETS, None).unwrap();
+        let dir = std::env::temp_dir().join(format!("ssmt4-validate-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        let dir_str = dir.to_string_lossy().to_string();
+
+        let raw = json!({
+            "basic": { "gamePreset": "NotAGame" },
+            "threeDMigoto": {
+                "installDir": dir_str,
+                "targetExePath": dir_str,
+                "delay": -5,
+                "autoExitSeconds": "",
+                "launchProfiles": [{ "name": "A" }, { "name": "A", "delay": 100 }]
+            }
+        });
+        let issues = validate_value(&raw, &registry);
+        let fields: Vec<&str> = issues.iter().map(|i| i.field.as_str()).collect();
+        assert_eq!(
+            fields,
+            [
+                "basic.gamePreset",
+                "threeDMigoto.installDir",
+                "threeDMigoto.targetExePath",
+                "threeDMigoto.delay",
+                "threeDMigoto.launchProfiles[1].name"
+            ]
+        );
+        assert!(issues[2].message.contains("folder"));
+
+        let mut config = GameConfig::default();
+        config.three_d_migoto.install_dir = Some(dir_str);
+        std::fs::write(dir.join("d3dx.ini"), "").unwrap();
+        assert!(validate_config(&config, &registry).is_empty());
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
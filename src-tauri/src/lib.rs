@@ -2,7 +2,7 @@ pub mod configs;
 pub mod utils;
 mod commands; // 引入统一的命令模块
 
-use crate::configs::app_config::AppConfig;
+use crate::configs::app_config::{AppConfig, SettingsLoadFailed};
 use std::sync::Mutex;
 use tauri::{Manager, WindowEvent};
 
@@ -20,8 +20,17 @@ pub fn run() {
         .setup(|app| {
             let main_window = app.get_webview_window("main").unwrap();
             
+            // Damaged config files found from here on are reported to the UI
+            configs::recovery::set_app_handle(app.handle().clone());
+
             // 1. 加载配置到内存 (如果失败则使用默认值)
-            let config = AppConfig::load().unwrap_or_default();
+            let (config, load_failed) = match AppConfig::load() {
+                Ok(config) => (config, false),
+                Err(e) => {
+                    eprintln!("Failed to load settings, using defaults and leaving the file as is: {}", e);
+                    (AppConfig::default(), true)
+                }
+            };
             
             // 2. 根据配置初始化窗口大小
             let _ = main_window.set_size(tauri::Size::Logical(tauri::LogicalSize {
@@ -31,14 +40,22 @@ pub fn run() {
 
             // 3. 将配置包装在 Mutex 中，并托管给 Tauri 全局状态
             app.manage(Mutex::new(config));
+            app.manage(SettingsLoadFailed(load_failed));
             
             // 4. 初始化 ModWatcher 状态
             app.manage(commands::mod_manager::ModWatcher(Mutex::new(None)));
 
+            // 5. 初始化游戏会话监控状态
+            app.manage(commands::game_session::GameSessions(Mutex::new(Default::default())));
+
             Ok(())
         })
         .on_window_event(|window, event| {
             if let WindowEvent::CloseRequested { .. } = event {
+                // Saving the defaults now would replace the settings that failed to load
+                if window.state::<SettingsLoadFailed>().0 {
+                    return;
+                }
                 if let Ok(size) = window.inner_size() {
                     let scale_factor = window.scale_factor().unwrap_or(1.0);
                     let logical_size = size.to_logical::<f64>(scale_factor);
@@ -64,10 +81,18 @@ pub fn run() {
             commands::process::run_resource_executable,
             commands::game_scanner::scan_games,
             commands::game_scanner::set_game_visibility,
+            commands::game_scanner::set_game_favorite,
+            commands::game_scanner::reorder_games,
+            commands::game_scanner::set_game_categories,
+            commands::game_scanner::get_game_categories,
+            commands::game_scanner::set_game_category_list,
             commands::game_config::load_game_config,
             commands::game_config::save_game_config,
+            commands::game_config::validate_game_config,
             commands::game_config::create_new_config,
             commands::game_config::delete_game_config_folder,
+            commands::game_config::rename_game_config,
+            commands::game_config::duplicate_game_config,
             commands::game_config::set_game_background,
             commands::game_config::set_game_icon,
             commands::game_config::update_game_background,
@@ -75,6 +100,8 @@ pub fn run() {
             commands::game_config::install_3dmigoto_update,
             commands::game_launcher::start_game,
             commands::game_launcher::check_3dmigoto_integrity,
+            commands::game_launcher::preflight_launch,
+            commands::game_launcher::unpack_d3d11,
             commands::mod_manager::watch_mods,
             commands::mod_manager::unwatch_mods,
             commands::game_launcher::toggle_symlink,
@@ -88,7 +115,28 @@ pub fn run() {
             commands::mod_manager::open_mod_group_folder,
             commands::mod_manager::rename_mod_group,
             commands::mod_manager::move_mod_to_group,
-            commands::mod_manager::delete_mod_group
+            commands::mod_manager::delete_mod_group,
+            commands::migoto_config::resolve_3dmigoto_config,
+            commands::mod_variables::list_mod_variables,
+            commands::mod_variables::set_mod_variable,
+            commands::mod_variables::reset_mod_variable,
+            commands::mod_variables::prune_mod_variables,
+            commands::hash_remap::remap_mod_hashes,
+            commands::hash_remap::list_hash_remap_backups,
+            commands::hash_remap::revert_hash_remap,
+            commands::shader_fixes::scan_shader_fixes,
+            commands::shader_fixes::toggle_shader_fix,
+            commands::shader_fixes::clear_shader_cache,
+            commands::game_session::get_play_history,
+            commands::game_session::get_running_games,
+            commands::diagnostics::create_diagnostic_bundle,
+            commands::game_package::export_game_setup,
+            commands::game_package::preview_game_setup,
+            commands::game_package::import_game_setup,
+            commands::game_detector::detect_game_installs,
+            configs::presets::list_game_presets,
+            configs::presets::reload_game_presets,
+            configs::recovery::take_config_recovery_notices
         ])
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
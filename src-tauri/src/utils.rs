@@ -1,2 +1,9 @@
 pub mod file_manager;
-pub mod ini_manager;
\ No newline at end of file
+pub mod ini_manager;
+pub mod ini_resolver;
+pub mod launcher;
+pub mod hooks;
+pub mod launch_backup;
+pub mod upx;
+pub mod atomic_file;
+pub mod wine_stage;
\ No newline at end of file
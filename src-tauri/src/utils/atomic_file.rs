@@ -0,0 +1,190 @@
+use std::collections::HashMap;
+use std::fs::{self, File};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Condvar, Mutex};
+use std::thread::{self, ThreadId};
+use std::time::Duration;
+
+// Writes go to a temp file in the same folder, are flushed to disk and then
+// renamed over the target, so a crash leaves either the old or the new file,
+// never half of one. Commands that load, change and save a file hold lock()
+// around the whole sequence so concurrent commands cannot drop each other's
+// changes.
+
+static TEMP_COUNTER: AtomicU64 = AtomicU64::new(0);
+
+// Locked path -> (owning thread, depth). Re-entrant, so a locked
+// load-modify-save can call helpers that lock the same file again.
+static LOCKED: Mutex<Option<HashMap<PathBuf, (ThreadId, usize)>>> = Mutex::new(None);
+static RELEASED: Condvar = Condvar::new();
+
+pub struct FileLock {
+    key: PathBuf,
+}
+
+// Same key for "Games/x/Config.json" and "Games/./x/Config.json", even before the file exists
+fn lock_key(path: &Path) -> PathBuf {
+    let key = match (path.parent(), path.file_name()) {
+        (Some(parent), Some(name)) => fs::canonicalize(parent)
+            .map(|p| p.join(name))
+            .unwrap_or_else(|_| path.to_path_buf()),
+        _ => path.to_path_buf(),
+    };
+    // NTFS is case-insensitive
+    if cfg!(windows) {
+        PathBuf::from(key.to_string_lossy().to_lowercase())
+    } else {
+        key
+    }
+}
+
+// Blocks until no other thread holds the file. Released on drop.
+pub fn lock(path: &Path) -> FileLock {
+    let key = lock_key(path);
+    let me = thread::current().id();
+    let mut guard = LOCKED.lock().unwrap();
+    loop {
+        let locked = guard.get_or_insert_with(HashMap::new);
+        match locked.get_mut(&key) {
+            None => {
+                locked.insert(key.clone(), (me, 1));
+                break;
+            }
+            Some((owner, depth)) if *owner == me => {
+                *depth += 1;
+                break;
+            }
+            Some(_) => guard = RELEASED.wait(guard).unwrap(),
+        }
+    }
+    FileLock { key }
+}
+
+impl Drop for FileLock {
+    fn drop(&mut self) {
+        let mut guard = LOCKED.lock().unwrap();
+        if let Some(locked) = guard.as_mut() {
+            if let Some((_, depth)) = locked.get_mut(&self.key) {
+                *depth -= 1;
+                if *depth == 0 {
+                    locked.remove(&self.key);
+                }
+            }
+        }
+        RELEASED.notify_all();
+    }
+}
+
+// Writing through a symlink (e.g. a d3dx.ini linked from a shared folder)
+// replaces the link target, not the link
+fn resolve_target(path: &Path) -> PathBuf {
+    match fs::symlink_metadata(path) {
+        Ok(meta) if meta.file_type().is_symlink() => fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf()),
+        _ => path.to_path_buf(),
+    }
+}
+
+fn temp_path(target: &Path) -> PathBuf {
+    let name = target.file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_default();
+    let n = TEMP_COUNTER.fetch_add(1, Ordering::Relaxed);
+    target.with_file_name(format!(".{}.{}-{}.tmp", name, std::process::id(), n))
+}
+
+// Antivirus and the search indexer briefly open freshly written files on
+// Windows, which makes the replacing rename fail with "access denied"
+fn rename_with_retry(from: &Path, to: &Path) -> io::Result<()> {
+    let mut attempt = 0;
+    loop {
+        match fs::rename(from, to) {
+            Err(e) if cfg!(windows) && e.kind() == io::ErrorKind::PermissionDenied && attempt < 5 => {
+                attempt += 1;
+                thread::sleep(Duration::from_millis(20 * attempt));
+            }
+            result => return result,
+        }
+    }
+}
+
+pub fn write(path: &Path, contents: impl AsRef<[u8]>) -> io::Result<()> {
+    let _lock = lock(path);
+    let target = resolve_target(path);
+    let temp = temp_path(&target);
+
+    let result = (|| {
+        let mut file = File::create(&temp)?;
+        file.write_all(contents.as_ref())?;
+        if let Ok(meta) = fs::metadata(&target) {
+            file.set_permissions(meta.permissions())?;
+        }
+        file.sync_all()?;
+        drop(file);
+        rename_with_retry(&temp, &target)
+    })();
+
+    if result.is_err() {
+        let _ = fs::remove_file(&temp);
+        return result;
+    }
+
+    // Makes the rename itself durable; Windows has no directory handles for this
+    #[cfg(unix)]
+    if let Some(parent) = target.parent().filter(|p| !p.as_os_str().is_empty()) {
+        if let Ok(dir) = File::open(parent) {
+            let _ = dir.sync_all();
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("ssmt4-atomic-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn write_replaces_file_and_leaves_no_temp_files() {
+        let dir = temp_dir("write");
+        let path = dir.join("settings.json");
+        fs::write(&path, "old content that is longer").unwrap();
+
+        write(&path, "new").unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), "new");
+        assert_eq!(fs::read_dir(&dir).unwrap().count(), 1);
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn locked_updates_from_many_threads_are_not_lost() {
+        let dir = temp_dir("lock");
+        let path = Arc::new(dir.join("counter.txt"));
+        write(&path, "0").unwrap();
+
+        let threads: Vec<_> = (0..8)
+            .map(|_| {
+                let path = path.clone();
+                thread::spawn(move || {
+                    for _ in 0..10 {
+                        let _lock = lock(&path);
+                        let n: u32 = fs::read_to_string(&*path).unwrap().parse().unwrap();
+                        // write() takes the same lock again on this thread
+                        write(&path, (n + 1).to_string()).unwrap();
+                    }
+                })
+            })
+            .collect();
+        for t in threads {
+            t.join().unwrap();
+        }
+        assert_eq!(fs::read_to_string(&*path).unwrap(), "80");
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
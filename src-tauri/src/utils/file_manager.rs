@@ -39,57 +39,54 @@ pub fn get_global_games_dir(app: &AppHandle) -> PathBuf {
     target_games_dir
 }
 
-use tauri_plugin_dialog::{DialogExt, MessageDialogKind};
-
-pub fn copy_boot_files(app: &AppHandle, target_dir: &Path) {
-    let resource_dir = match app.path().resource_dir() {
-        Ok(p) => p,
-        Err(e) => {
-            eprintln!("Failed to get resource dir: {}", e);
-            return; 
+// Bundled resource (d3d11.dll, Run.exe, upx.exe, ...) with dev environment fallbacks
+pub fn find_bundled_resource(app: &AppHandle, filename: &str) -> Option<PathBuf> {
+    if let Ok(resource_dir) = app.path().resource_dir() {
+        let p = resource_dir.join(filename);
+        if p.exists() {
+            return Some(p);
         }
-    };
-    
-    let files = ["d3d11.dll", "d3dcompiler_47.dll", "Run.exe"];
-    
-    for filename in files {
+    }
+
+    let dev_path = PathBuf::from("resources").join(filename);
+    if dev_path.exists() {
+        return Some(dev_path);
+    }
+    let dev_path_2 = PathBuf::from("src-tauri/resources").join(filename);
+    if dev_path_2.exists() {
+        return Some(dev_path_2);
+    }
+    None
+}
+
+// Files start_game refreshes in the 3Dmigoto folder, and whether a launch
+// needs them. d3dcompiler_47.dll is not bundled; Windows 10+ ships one.
+pub const BOOT_FILES: [(&str, bool); 3] = [("d3d11.dll", true), ("d3dcompiler_47.dll", false), ("Run.exe", true)];
+
+// Returns a message for every file that could not be put in place
+pub fn copy_boot_files(app: &AppHandle, target_dir: &Path) -> Vec<String> {
+    let mut problems = Vec::new();
+
+    for (filename, required) in BOOT_FILES {
         let dest_path = target_dir.join(filename);
-        // Helper to check existence and copy
-        let mut source_to_use = resource_dir.join(filename);
-        
-        if !source_to_use.exists() {
-            // Fallback for dev environment
-            let dev_path = PathBuf::from("resources").join(filename);
-            let dev_path_2 = PathBuf::from("src-tauri/resources").join(filename);
-
-            if dev_path.exists() {
-                source_to_use = dev_path;
-            } else if dev_path_2.exists() {
-                source_to_use = dev_path_2;
-            } else {
-                 let msg = format!("Resource file '{}' not found.", filename);
-                 eprintln!("{}", msg);
-                 app.dialog()
-                    .message(&msg)
-                    .title("Error Missing File")
-                    .kind(MessageDialogKind::Error)
-                    .show(|_| {});
-                 continue;
+        let Some(source_to_use) = find_bundled_resource(app, filename) else {
+            if required {
+                let msg = format!("Resource file '{}' not found.", filename);
+                eprintln!("{}", msg);
+                problems.push(msg);
             }
-        }
-        
+            continue;
+        };
+
         if let Err(e) = fs::copy(&source_to_use, &dest_path) {
-             let msg = format!("Failed to copy {}: {}.\nPlease ensure the game is closed.", filename, e);
-             eprintln!("{}", msg);
-             app.dialog()
-                .message(&msg)
-                .title("Copy Failed")
-                .kind(MessageDialogKind::Error)
-                .show(|_| {});
+            let msg = format!("Failed to copy {}: {}. Please ensure the game is closed.", filename, e);
+            eprintln!("{}", msg);
+            problems.push(msg);
         } else {
             println!("Copied {} successfully.", filename);
         }
     }
+    problems
 }
 
 // 辅助：查找打包自带的资源目录
@@ -124,7 +121,7 @@ fn find_source_games_dir(app: &AppHandle) -> Option<PathBuf> {
 }
 
 // 递归拷贝目录，如果目标文件存在则跳过
-fn copy_dir_recursive(src: &Path, dst: &Path) -> std::io::Result<()> {
+pub fn copy_dir_recursive(src: &Path, dst: &Path) -> std::io::Result<()> {
     if !dst.exists() {
         fs::create_dir_all(dst)?;
     }
@@ -147,6 +144,25 @@ fn copy_dir_recursive(src: &Path, dst: &Path) -> std::io::Result<()> {
     Ok(())
 }
 
+// Joins a relative path that came from the frontend or a manifest onto base,
+// refusing absolute paths, ".." and links that lead out of base
+pub fn join_inside(base: &Path, rel: &str) -> Result<PathBuf, String> {
+    let rel_path = Path::new(rel);
+    if rel_path
+        .components()
+        .any(|c| !matches!(c, std::path::Component::Normal(_) | std::path::Component::CurDir))
+    {
+        return Err(format!("Invalid relative path: {}", rel));
+    }
+    let joined = base.join(rel_path);
+    if let (Ok(real), Ok(real_base)) = (fs::canonicalize(&joined), fs::canonicalize(base)) {
+        if !real.starts_with(&real_base) {
+            return Err(format!("Path leads outside {:?}: {}", base, rel));
+        }
+    }
+    Ok(joined)
+}
+
 pub fn check_and_create_dir(path: &Path) -> std::io::Result<()> {
     if !path.exists() {
         fs::create_dir_all(path)?;
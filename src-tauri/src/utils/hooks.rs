@@ -0,0 +1,219 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::path::Path;
+use std::process::Stdio;
+use std::time::Duration;
+
+const DEFAULT_TIMEOUT_SECS: u64 = 60;
+
+// A user command run before launch or after the game exits.
+// No shell is involved: scripts are run as e.g. command = "powershell", args = ["-File", "x.ps1"].
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
+#[serde(rename_all = "camelCase")]
+#[serde(default)]
+pub struct HookCommand {
+    pub name: String,
+    pub command: String,
+    pub args: Vec<String>,
+    pub working_dir: String,
+    pub env: HashMap<String, String>,
+    pub timeout_secs: Option<u64>,
+    pub abort_on_failure: bool, // Pre-launch only: stop start_game if this hook fails
+    pub enabled: Option<bool>,  // Missing means enabled
+}
+
+#[derive(Debug, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct HookResult {
+    pub name: String,
+    pub command: String,
+    pub success: bool,
+    pub exit_code: Option<i32>,
+    pub timed_out: bool,
+    pub error: Option<String>,
+}
+
+impl HookResult {
+    // One line for LaunchResult.warnings: which command failed and how
+    pub fn failure_summary(&self) -> String {
+        let status = match (self.timed_out, self.exit_code) {
+            (true, _) => "timed out".to_string(),
+            (false, Some(code)) => format!("exit code {}", code),
+            (false, None) => self.error.clone().unwrap_or_else(|| "no exit code".to_string()),
+        };
+        format!("Hook '{}' ({}) failed: {}", self.name, self.command, status)
+    }
+}
+
+impl HookCommand {
+    fn display_name(&self) -> String {
+        if self.name.trim().is_empty() {
+            self.command.clone()
+        } else {
+            self.name.clone()
+        }
+    }
+}
+
+fn append_log(log_path: &Path, text: &str) {
+    let file = OpenOptions::new().create(true).append(true).open(log_path);
+    match file {
+        Ok(mut f) => {
+            let _ = f.write_all(text.as_bytes());
+        }
+        Err(e) => eprintln!("[Hooks] Failed to open hook log {:?}: {}", log_path, e),
+    }
+}
+
+pub async fn run_hook(hook: &HookCommand, stage: &str, log_path: &Path) -> HookResult {
+    let name = hook.display_name();
+    let timeout = Duration::from_secs(hook.timeout_secs.unwrap_or(DEFAULT_TIMEOUT_SECS));
+    let started = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0);
+
+    println!("[Hooks] Running {} hook '{}': {} {:?}", stage, name, hook.command, hook.args);
+
+    let mut cmd = tokio::process::Command::new(&hook.command);
+    cmd.args(&hook.args)
+        .envs(&hook.env)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true);
+    if !hook.working_dir.trim().is_empty() {
+        cmd.current_dir(&hook.working_dir);
+    }
+    #[cfg(windows)]
+    cmd.creation_flags(0x08000000); // CREATE_NO_WINDOW
+
+    let mut result = HookResult {
+        name: name.clone(),
+        command: hook.command.clone(),
+        success: false,
+        exit_code: None,
+        timed_out: false,
+        error: None,
+    };
+    let mut log = format!("===== [{}] {} hook '{}': {} {:?}\n", started, stage, name, hook.command, hook.args);
+
+    match cmd.spawn() {
+        Err(e) => {
+            result.error = Some(format!("Failed to start hook '{}': {}", name, e));
+        }
+        Ok(child) => match tokio::time::timeout(timeout, child.wait_with_output()).await {
+            Err(_) => {
+                // Dropping the future kills the child (kill_on_drop)
+                result.timed_out = true;
+                result.error = Some(format!("Hook '{}' timed out after {:?}", name, timeout));
+            }
+            Ok(Err(e)) => {
+                result.error = Some(format!("Hook '{}' failed: {}", name, e));
+            }
+            Ok(Ok(output)) => {
+                result.exit_code = output.status.code();
+                result.success = output.status.success();
+                if !result.success {
+                    result.error = Some(format!("Hook '{}' exited with {:?}", name, output.status.code()));
+                }
+                log.push_str(&String::from_utf8_lossy(&output.stdout));
+                let stderr = String::from_utf8_lossy(&output.stderr);
+                if !stderr.is_empty() {
+                    log.push_str("--- stderr ---\n");
+                    log.push_str(&stderr);
+                }
+            }
+        },
+    }
+
+    match &result.error {
+        Some(e) => log.push_str(&format!("\n=> {}\n\n", e)),
+        None => log.push_str(&format!("\n=> exit code {:?}\n\n", result.exit_code)),
+    }
+    append_log(log_path, &log);
+
+    result
+}
+
+// Runs enabled hooks in order. Stops at the first failing hook marked abort_on_failure
+// and returns its error; other failures are only reported in the results.
+pub async fn run_hooks(hooks: &[HookCommand], stage: &str, log_path: &Path) -> Result<Vec<HookResult>, String> {
+    let mut results = Vec::new();
+    for hook in hooks.iter().filter(|h| h.enabled.unwrap_or(true) && !h.command.trim().is_empty()) {
+        let result = run_hook(hook, stage, log_path).await;
+        let abort = !result.success && hook.abort_on_failure;
+        let error = result.error.clone();
+        results.push(result);
+        if abort {
+            return Err(error.unwrap_or_else(|| format!("{} hook failed", stage)));
+        }
+    }
+    Ok(results)
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+
+    fn sh(name: &str, script: &str) -> HookCommand {
+        HookCommand {
+            name: name.to_string(),
+            command: "sh".to_string(),
+            args: vec!["-c".to_string(), script.to_string()],
+            ..Default::default()
+        }
+    }
+
+    fn log_path(name: &str) -> std::path::PathBuf {
+        let path = std::env::temp_dir().join(format!("ssmt4-hooks-{}-{}.txt", name, std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        path
+    }
+
+    #[tokio::test]
+    async fn env_reaches_the_hook_and_output_is_logged() {
+        let log = log_path("env");
+        let mut hook = sh("echo env", "echo \"value=$SSMT4_HOOK_TEST\"");
+        hook.env.insert("SSMT4_HOOK_TEST".to_string(), "42".to_string());
+
+        let results = run_hooks(&[hook], "pre-launch", &log).await.unwrap();
+        assert!(results[0].success);
+        assert!(std::fs::read_to_string(&log).unwrap().contains("value=42"));
+        let _ = std::fs::remove_file(&log);
+    }
+
+    #[tokio::test]
+    async fn timeout_kills_the_hook() {
+        let log = log_path("timeout");
+        let mut hook = sh("sleeper", "sleep 5");
+        hook.timeout_secs = Some(1);
+
+        let started = std::time::Instant::now();
+        let results = run_hooks(&[hook], "pre-launch", &log).await.unwrap();
+        assert!(started.elapsed() < Duration::from_secs(4));
+        assert!(results[0].timed_out);
+        assert_eq!(results[0].failure_summary(), "Hook 'sleeper' (sh) failed: timed out");
+        let _ = std::fs::remove_file(&log);
+    }
+
+    #[tokio::test]
+    async fn only_abort_on_failure_hooks_stop_the_sequence() {
+        let log = log_path("abort");
+        let mut disabled = sh("disabled", "exit 0");
+        disabled.enabled = Some(false);
+        let mut abort = sh("must pass", "exit 2");
+        abort.abort_on_failure = true;
+        let hooks = [sh("may fail", "exit 3"), disabled, abort, sh("never runs", "exit 0")];
+
+        let err = run_hooks(&hooks, "pre-launch", &log).await.unwrap_err();
+        assert!(err.contains("must pass"));
+
+        let results = run_hooks(&hooks[..2], "pre-launch", &log).await.unwrap();
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].failure_summary(), "Hook 'may fail' (sh) failed: exit code 3");
+        let _ = std::fs::remove_file(&log);
+    }
+}
@@ -1,20 +1,207 @@
+use crate::utils::atomic_file;
+use encoding_rs::{Encoding, GBK, SHIFT_JIS, UTF_16BE, UTF_16LE, UTF_8};
 use std::fs;
 use std::path::{Path, PathBuf};
-use std::io::{Write};
 
 pub struct IniManager {
     path: PathBuf,
     content: String,
+    encoding: &'static Encoding,
+    has_bom: bool,
+    crlf: bool,
+    lossy: bool, // Undecodable bytes were replaced with U+FFFD, so saving would destroy them
+}
+
+// Mod authors save ini files as UTF-8, GBK, Shift-JIS or UTF-16, with or without BOM.
+fn detect_encoding(bytes: &[u8]) -> (&'static Encoding, bool) {
+    if let Some((encoding, _bom_len)) = Encoding::for_bom(bytes) {
+        return (encoding, true);
+    }
+
+    // BOM-less UTF-16: ASCII-heavy ini text leaves every other byte zero
+    if bytes.len() >= 4 {
+        let zeros_even = bytes.iter().step_by(2).filter(|b| **b == 0).count();
+        let zeros_odd = bytes.iter().skip(1).step_by(2).filter(|b| **b == 0).count();
+        let half = bytes.len() / 2;
+        if zeros_odd * 3 > half {
+            return (UTF_16LE, false);
+        }
+        if zeros_even * 3 > half {
+            return (UTF_16BE, false);
+        }
+    }
+
+    if std::str::from_utf8(bytes).is_ok() {
+        return (UTF_8, false);
+    }
+
+    let gbk_ok = GBK.decode_without_bom_handling_and_without_replacement(bytes).is_some();
+    let sjis = SHIFT_JIS.decode_without_bom_handling_and_without_replacement(bytes);
+
+    // Most Shift-JIS byte sequences are also valid GBK, so only pick Shift-JIS
+    // when GBK fails or the Shift-JIS reading actually contains kana.
+    match (gbk_ok, sjis) {
+        (true, Some(text)) if text.chars().any(|c| ('\u{3040}'..='\u{30FF}').contains(&c)) => (SHIFT_JIS, false),
+        (true, _) => (GBK, false),
+        (false, Some(_)) => (SHIFT_JIS, false),
+        // Undecodable either way: read as lossy UTF-8 like the rest of the app, save() refuses
+        (false, None) => (UTF_8, false),
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct IniEntry {
+    pub key: String,
+    pub value: String,
+    pub line: usize, // 1-based line number in the file
+}
+
+#[derive(Debug, Clone)]
+pub struct IniSection {
+    pub name: String,
+    pub entries: Vec<IniEntry>,
 }
 
 impl IniManager {
     pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, String> {
         let path = path.as_ref().to_path_buf();
         println!("[IniManager] Loading ini from: {:?}", path);
-        let content =
-            fs::read_to_string(&path).map_err(|e| format!("Failed to read ini file: {}", e))?;
+        let bytes = fs::read(&path).map_err(|e| format!("Failed to read ini file: {}", e))?;
+
+        let (encoding, has_bom) = detect_encoding(&bytes);
+        let (content, lossy) = if has_bom {
+            encoding.decode_with_bom_removal(&bytes)
+        } else {
+            encoding.decode_without_bom_handling(&bytes)
+        };
+        let content = content.into_owned();
+        let crlf = content.contains("\r\n");
+
+        if encoding != UTF_8 || has_bom {
+            println!("[IniManager] Detected encoding: {} (BOM: {})", encoding.name(), has_bom);
+        }
+
+        if lossy {
+            eprintln!("[IniManager] {:?} has bytes that are not valid {}, it will be read-only", path, encoding.name());
+        }
 
-        Ok(Self { path, content })
+        Ok(Self { path, content, encoding, has_bom, crlf, lossy })
+    }
+
+    pub fn encoding_name(&self) -> &'static str {
+        self.encoding.name()
+    }
+
+    pub fn has_bom(&self) -> bool {
+        self.has_bom
+    }
+
+    // save() refuses files like this
+    pub fn is_lossy(&self) -> bool {
+        self.lossy
+    }
+
+    // Re-encode in the file's original encoding, BOM and line endings
+    fn encode_content(&self) -> Result<Vec<u8>, String> {
+        let text = if self.crlf {
+            self.content.replace("\r\n", "\n").replace('\n', "\r\n")
+        } else {
+            self.content.clone()
+        };
+
+        let mut bytes = Vec::with_capacity(text.len() + 3);
+        if self.encoding == UTF_16LE || self.encoding == UTF_16BE {
+            // encoding_rs only decodes UTF-16, so encode it by hand
+            let le = self.encoding == UTF_16LE;
+            if self.has_bom {
+                bytes.extend_from_slice(if le { &[0xFF, 0xFE] } else { &[0xFE, 0xFF] });
+            }
+            for unit in text.encode_utf16() {
+                bytes.extend_from_slice(&if le { unit.to_le_bytes() } else { unit.to_be_bytes() });
+            }
+            return Ok(bytes);
+        }
+
+        if self.has_bom && self.encoding == UTF_8 {
+            bytes.extend_from_slice(&[0xEF, 0xBB, 0xBF]);
+        }
+        let (encoded, _, unmappable) = self.encoding.encode(&text);
+        if unmappable {
+            return Err(format!(
+                "Content contains characters that cannot be saved as {}: {:?}",
+                self.encoding.name(),
+                self.path
+            ));
+        }
+        bytes.extend_from_slice(&encoded);
+        Ok(bytes)
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn content(&self) -> &str {
+        &self.content
+    }
+
+    // Read-only parse of the whole file, in file order.
+    // Keys before the first section header are dropped, like 3Dmigoto does.
+    pub fn sections(&self) -> Vec<IniSection> {
+        let mut sections: Vec<IniSection> = Vec::new();
+
+        for (i, line) in self.content.lines().enumerate() {
+            let trimmed = line.trim();
+            if trimmed.is_empty() || trimmed.starts_with(';') || trimmed.starts_with('#') {
+                continue;
+            }
+
+            let clean_line = trimmed.split(';').next().unwrap_or("").trim();
+            if clean_line.starts_with('[') && clean_line.ends_with(']') {
+                sections.push(IniSection {
+                    name: clean_line[1..clean_line.len() - 1].trim().to_string(),
+                    entries: Vec::new(),
+                });
+                continue;
+            }
+
+            if let (Some(section), Some(eq_idx)) = (sections.last_mut(), trimmed.find('=')) {
+                section.entries.push(IniEntry {
+                    key: trimmed[..eq_idx].trim().to_string(),
+                    value: trimmed[eq_idx + 1..].trim().to_string(),
+                    line: i + 1,
+                });
+            }
+        }
+
+        sections
+    }
+
+    pub fn get(&self, section: &str, key: &str) -> Option<String> {
+        self.sections()
+            .into_iter()
+            .filter(|s| s.name.eq_ignore_ascii_case(section))
+            .flat_map(|s| s.entries)
+            .rfind(|e| e.key.eq_ignore_ascii_case(key))
+            .map(|e| e.value)
+    }
+
+    // Replace a whole line (1-based), used for edits that must keep the surrounding layout
+    pub fn set_line(&mut self, line: usize, text: &str) {
+        let mut lines: Vec<String> = self.content.lines().map(|s| s.to_string()).collect();
+        if line >= 1 && line <= lines.len() {
+            lines[line - 1] = text.to_string();
+            self.replace_lines(lines);
+        }
+    }
+
+    // Keeps the final newline, so a load/change/save round trip only differs in the changed line
+    fn replace_lines(&mut self, lines: Vec<String>) {
+        let trailing_newline = self.content.ends_with('\n');
+        self.content = lines.join("\n");
+        if trailing_newline {
+            self.content.push('\n');
+        }
     }
 
     pub fn set(&mut self, section: &str, key: &str, value: &str) {
@@ -139,7 +326,7 @@ impl IniManager {
             }
         }
 
-        self.content = lines.join("\n"); // Using \n for consistency
+        self.replace_lines(lines); // Using \n for consistency
     }
 
     pub fn remove_key(&mut self, section: &str, key: &str) {
@@ -182,19 +369,90 @@ impl IniManager {
             lines.remove(i);
         }
 
-        self.content = lines.join("\n");
+        self.replace_lines(lines);
     }
 
     pub fn save(&self) -> Result<(), String> {
         println!("[IniManager] Saving ini to: {:?}", self.path);
-        let mut file = fs::File::create(&self.path)
-            .map_err(|e| format!("Failed to open file for writing: {}", e))?;
-        file.write_all(self.content.as_bytes())
+        if self.lossy {
+            return Err(format!(
+                "Refusing to save {:?}: its encoding could not be detected and saving would corrupt it",
+                self.path
+            ));
+        }
+        let bytes = self.encode_content()?;
+        atomic_file::write(&self.path, &bytes)
             .map_err(|e| format!("Failed to write content: {}", e))?;
         println!(
-            "[IniManager] Successfully wrote {} bytes.",
-            self.content.len()
+            "[IniManager] Successfully wrote {} bytes ({}).",
+            bytes.len(),
+            self.encoding.name()
         );
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_ini(name: &str, bytes: &[u8]) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("ssmt4-ini-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join(name);
+        fs::write(&path, bytes).unwrap();
+        path
+    }
+
+    // Loads, changes one value, saves and checks the bytes are still in the original encoding
+    fn round_trip(name: &str, bytes: &[u8], expected_encoding: &str, expected_bom: bool) -> Vec<u8> {
+        let path = temp_ini(name, bytes);
+        let mut ini = IniManager::load(&path).unwrap();
+        assert_eq!(ini.encoding_name(), expected_encoding);
+        assert_eq!(ini.has_bom(), expected_bom);
+        ini.set("Constants", "$x", "2");
+        ini.save().unwrap();
+        let saved = fs::read(&path).unwrap();
+        let _ = fs::remove_file(&path);
+        saved
+    }
+
+    #[test]
+    fn gbk_round_trip() {
+        let text = "; 角色模组\r\n[Constants]\r\n$x = 1\r\n";
+        let (bytes, _, _) = GBK.encode(text);
+        let saved = round_trip("gbk.ini", &bytes, "GBK", false);
+        assert_eq!(saved, GBK.encode(&text.replace("$x = 1", "$x = 2")).0.as_ref());
+    }
+
+    #[test]
+    fn shift_jis_round_trip() {
+        let text = "; キャラクター\n[Constants]\n$x = 1\n";
+        let (bytes, _, _) = SHIFT_JIS.encode(text);
+        let saved = round_trip("sjis.ini", &bytes, "Shift_JIS", false);
+        assert_eq!(saved, SHIFT_JIS.encode(&text.replace("$x = 1", "$x = 2")).0.as_ref());
+    }
+
+    #[test]
+    fn utf16_bom_round_trip() {
+        let text = "[Constants]\r\n$x = 1\r\n";
+        let mut bytes = vec![0xFF, 0xFE];
+        bytes.extend(text.encode_utf16().flat_map(|u| u.to_le_bytes()));
+        let saved = round_trip("utf16.ini", &bytes, "UTF-16LE", true);
+        let mut expected = vec![0xFF, 0xFE];
+        expected.extend(text.replace("$x = 1", "$x = 2").encode_utf16().flat_map(|u| u.to_le_bytes()));
+        assert_eq!(saved, expected);
+    }
+
+    #[test]
+    fn undecodable_file_is_read_but_not_saved() {
+        let bytes = b"; \xff\xfe\xff\x80\x80\n[Constants]\n$x = 1\n";
+        let path = temp_ini("lossy.ini", bytes);
+        let mut ini = IniManager::load(&path).unwrap();
+        assert_eq!(ini.get("Constants", "$x").as_deref(), Some("1"));
+        ini.set("Constants", "$x", "2");
+        assert!(ini.save().is_err());
+        assert_eq!(fs::read(&path).unwrap(), bytes);
+        let _ = fs::remove_file(&path);
+    }
+}
@@ -0,0 +1,318 @@
+use crate::utils::ini_manager::IniManager;
+use serde::Serialize;
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+// Sections that hold command lists: 3Dmigoto runs the copy from every loaded file.
+const MERGED_SECTIONS: [&str; 3] = ["Constants", "Present", "ClearRenderTargetView"];
+
+// Sections that are only honoured in d3dx.ini itself.
+const MAIN_ONLY_SECTIONS: [&str; 9] = [
+    "Loader", "System", "Device", "Stereo", "Rendering", "Hunting", "Logging", "Include", "Profile",
+];
+
+#[derive(Debug, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct LoadedIniFile {
+    pub path: String,
+    pub directive: String, // "main", "include" or "include_recursive"
+    pub included_from: Option<String>,
+    pub encoding: String,
+    pub has_bom: bool,
+}
+
+#[derive(Debug, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct EffectiveEntry {
+    pub key: String,
+    pub value: String,
+    pub source: String,
+    pub line: usize,
+}
+
+#[derive(Debug, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct EffectiveSection {
+    pub name: String,
+    pub entries: Vec<EffectiveEntry>,
+}
+
+#[derive(Debug, Serialize, Clone, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct EffectiveMigotoConfig {
+    pub loaded_files: Vec<LoadedIniFile>,
+    pub excluded_paths: Vec<String>,
+    pub missing_includes: Vec<String>,
+    pub global_sections: Vec<EffectiveSection>,
+}
+
+// Simple case-insensitive glob supporting '*' and '?', which is all exclude_recursive accepts.
+fn wildcard_match(pattern: &str, name: &str) -> bool {
+    let p: Vec<char> = pattern.to_lowercase().chars().collect();
+    let n: Vec<char> = name.to_lowercase().chars().collect();
+    let (mut pi, mut ni) = (0, 0);
+    let mut star: Option<(usize, usize)> = None;
+
+    while ni < n.len() {
+        if pi < p.len() && (p[pi] == '?' || p[pi] == n[ni]) {
+            pi += 1;
+            ni += 1;
+        } else if pi < p.len() && p[pi] == '*' {
+            star = Some((pi, ni));
+            pi += 1;
+        } else if let Some((sp, sn)) = star {
+            pi = sp + 1;
+            ni = sn + 1;
+            star = Some((sp, sn + 1));
+        } else {
+            return false;
+        }
+    }
+
+    while pi < p.len() && p[pi] == '*' {
+        pi += 1;
+    }
+    pi == p.len()
+}
+
+pub fn is_disabled_name(name: &str) -> bool {
+    name.get(..8).is_some_and(|p| p.eq_ignore_ascii_case("DISABLED"))
+}
+
+struct Resolver {
+    exclude_patterns: Vec<String>,
+    visited: HashSet<PathBuf>,
+    result: EffectiveMigotoConfig,
+}
+
+impl Resolver {
+    fn is_excluded(&self, name: &str) -> bool {
+        is_disabled_name(name) || self.exclude_patterns.iter().any(|p| wildcard_match(p, name))
+    }
+
+    fn load_file(&mut self, path: &Path, directive: &str, included_from: Option<&Path>) {
+        let key = fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf());
+        if !self.visited.insert(key) {
+            return;
+        }
+
+        let ini = match IniManager::load(path) {
+            Ok(ini) => ini,
+            Err(e) => {
+                eprintln!("[IniResolver] Skipping {:?}: {}", path, e);
+                self.result.missing_includes.push(path.to_string_lossy().to_string());
+                return;
+            }
+        };
+
+        self.result.loaded_files.push(LoadedIniFile {
+            path: path.to_string_lossy().to_string(),
+            directive: directive.to_string(),
+            included_from: included_from.map(|p| p.to_string_lossy().to_string()),
+            encoding: ini.encoding_name().to_string(),
+            has_bom: ini.has_bom(),
+        });
+
+        let sections = ini.sections();
+        let is_main = directive == "main";
+        let source = path.to_string_lossy().to_string();
+
+        for section in &sections {
+            let merged = MERGED_SECTIONS.iter().any(|s| s.eq_ignore_ascii_case(&section.name));
+            let main_only = MAIN_ONLY_SECTIONS.iter().any(|s| s.eq_ignore_ascii_case(&section.name));
+            if !(merged || (main_only && is_main)) {
+                continue;
+            }
+
+            let effective = match self
+                .result
+                .global_sections
+                .iter_mut()
+                .position(|s| s.name.eq_ignore_ascii_case(&section.name))
+            {
+                Some(idx) => &mut self.result.global_sections[idx],
+                None => {
+                    self.result.global_sections.push(EffectiveSection {
+                        name: section.name.clone(),
+                        entries: Vec::new(),
+                    });
+                    self.result.global_sections.last_mut().unwrap()
+                }
+            };
+
+            for entry in &section.entries {
+                if main_only && !section.name.eq_ignore_ascii_case("Include") {
+                    // Settings sections are last-wins, [Include] keys legitimately repeat
+                    effective.entries.retain(|e| !e.key.eq_ignore_ascii_case(&entry.key));
+                }
+                effective.entries.push(EffectiveEntry {
+                    key: entry.key.clone(),
+                    value: entry.value.clone(),
+                    source: source.clone(),
+                    line: entry.line,
+                });
+            }
+        }
+
+        // Follow [Include] directives relative to this file's folder
+        let base_dir = path.parent().map(|p| p.to_path_buf()).unwrap_or_default();
+        let includes: Vec<(String, String)> = sections
+            .iter()
+            .filter(|s| s.name.eq_ignore_ascii_case("Include"))
+            .flat_map(|s| s.entries.iter().map(|e| (e.key.to_lowercase(), e.value.clone())))
+            .collect();
+
+        // exclude_recursive applies regardless of where it appears in the section
+        for (key, value) in &includes {
+            if key == "exclude_recursive" && !value.is_empty() {
+                self.exclude_patterns.push(value.clone());
+            }
+        }
+
+        for (key, value) in includes {
+            if value.is_empty() {
+                continue;
+            }
+            let target = base_dir.join(&value);
+            match key.as_str() {
+                "include" => {
+                    if target.is_file() {
+                        self.load_file(&target, "include", Some(path));
+                    } else {
+                        self.result.missing_includes.push(target.to_string_lossy().to_string());
+                    }
+                }
+                "include_recursive" => {
+                    if target.is_dir() {
+                        self.walk_dir(&target, path);
+                    } else {
+                        self.result.missing_includes.push(target.to_string_lossy().to_string());
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    fn walk_dir(&mut self, dir: &Path, included_from: &Path) {
+        let mut entries: Vec<PathBuf> = match fs::read_dir(dir) {
+            Ok(rd) => rd.flatten().map(|e| e.path()).collect(),
+            Err(e) => {
+                eprintln!("[IniResolver] Failed to read {:?}: {}", dir, e);
+                return;
+            }
+        };
+        // 3Dmigoto walks in name order; keep it deterministic across filesystems
+        entries.sort_by_key(|p| p.file_name().unwrap_or_default().to_string_lossy().to_lowercase());
+
+        // Files of a folder are loaded before descending into its subfolders
+        let (dirs, files): (Vec<PathBuf>, Vec<PathBuf>) = entries.into_iter().partition(|p| p.is_dir());
+
+        for file in files {
+            let name = file.file_name().unwrap_or_default().to_string_lossy().to_string();
+            let is_ini = file
+                .extension()
+                .map(|e| e.to_string_lossy().eq_ignore_ascii_case("ini"))
+                .unwrap_or(false);
+            if !is_ini {
+                continue;
+            }
+            if self.is_excluded(&name) {
+                self.result.excluded_paths.push(file.to_string_lossy().to_string());
+                continue;
+            }
+            self.load_file(&file, "include_recursive", Some(included_from));
+        }
+
+        for sub in dirs {
+            let name = sub.file_name().unwrap_or_default().to_string_lossy().to_string();
+            if self.is_excluded(&name) {
+                self.result.excluded_paths.push(sub.to_string_lossy().to_string());
+                continue;
+            }
+            self.walk_dir(&sub, included_from);
+        }
+    }
+}
+
+// Follows the [Include] chain starting at <install_dir>/d3dx.ini the way 3Dmigoto does
+pub fn resolve_includes(install_dir: &Path) -> Result<EffectiveMigotoConfig, String> {
+    let d3dx_path = install_dir.join("d3dx.ini");
+    if !d3dx_path.exists() {
+        return Err(format!("d3dx.ini not found at {:?}", d3dx_path));
+    }
+
+    let mut resolver = Resolver {
+        exclude_patterns: Vec::new(),
+        visited: HashSet::new(),
+        result: EffectiveMigotoConfig::default(),
+    };
+    resolver.load_file(&d3dx_path, "main", None);
+
+    Ok(resolver.result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn write(root: &Path, rel: &str, content: &str) {
+        let path = root.join(rel);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, content).unwrap();
+    }
+
+    fn names(root: &Path, paths: impl Iterator<Item = String>) -> Vec<String> {
+        paths
+            .map(|p| Path::new(&p).strip_prefix(root).unwrap().to_string_lossy().replace('\\', "/"))
+            .collect()
+    }
+
+    #[test]
+    fn follows_includes_in_order_and_skips_excluded() {
+        let root = std::env::temp_dir().join(format!("ssmt4-resolver-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+        write(
+            &root,
+            "d3dx.ini",
+            "[Hunting]\nhunting = 2\n[Constants]\nglobal $a = 1\n[Include]\ninclude = Shared.ini\ninclude_recursive = Mods\ninclude = Missing.ini\nexclude_recursive = skip*\n",
+        );
+        write(&root, "Shared.ini", "[Hunting]\nhunting = 0\n[Constants]\nglobal $b = 2\n");
+        write(&root, "Mods/b.ini", "[Constants]\nglobal $c = 3\n");
+        write(&root, "Mods/a/a.ini", "[TextureOverrideA]\nhash = 1\n");
+        write(&root, "Mods/DISABLED_old.ini", "[Constants]\nglobal $x = 9\n");
+        write(&root, "Mods/DISABLED Folder/c.ini", "[Constants]\nglobal $y = 9\n");
+        write(&root, "Mods/skip_me.ini", "[Constants]\nglobal $z = 9\n");
+        write(&root, "Mods/readme.txt", "not an ini");
+
+        let resolved = resolve_includes(&root).unwrap();
+
+        // Files of a folder come before its subfolders
+        let loaded = names(&root, resolved.loaded_files.iter().map(|f| f.path.clone()));
+        assert_eq!(loaded, ["d3dx.ini", "Shared.ini", "Mods/b.ini", "Mods/a/a.ini"]);
+        assert_eq!(resolved.loaded_files[1].directive, "include");
+        assert_eq!(resolved.loaded_files[2].directive, "include_recursive");
+
+        let mut excluded = names(&root, resolved.excluded_paths.iter().cloned());
+        excluded.sort();
+        assert_eq!(excluded, ["Mods/DISABLED Folder", "Mods/DISABLED_old.ini", "Mods/skip_me.ini"]);
+        assert_eq!(names(&root, resolved.missing_includes.iter().cloned()), ["Missing.ini"]);
+
+        // [Constants] is merged from every file, [Hunting] only counts in d3dx.ini
+        let section = |name: &str| resolved.global_sections.iter().find(|s| s.name == name).unwrap();
+        let constants: Vec<&str> = section("Constants").entries.iter().map(|e| e.key.as_str()).collect();
+        assert_eq!(constants, ["global $a", "global $b", "global $c"]);
+        let hunting = &section("Hunting").entries;
+        assert_eq!((hunting.len(), hunting[0].value.as_str()), (1, "2"));
+        let _ = fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn wildcard_matches_like_exclude_recursive() {
+        assert!(wildcard_match("skip*", "Skip_Me.ini"));
+        assert!(wildcard_match("*.bak?", "d3dx.ini.bak1"));
+        assert!(!wildcard_match("a?c", "abbc"));
+        assert!(is_disabled_name("disabled_mod"));
+    }
+}
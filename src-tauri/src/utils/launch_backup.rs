@@ -0,0 +1,131 @@
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+// Copies of the files start_game overwrites, taken right before a launch so
+// "restore after exit" can put the 3Dmigoto folder back the way the user left it
+const BACKUP_DIR: &str = "SSMT4Backups/LaunchSession";
+const MANIFEST_NAME: &str = "manifest.json";
+const TRACKED_FILES: [&str; 4] = ["d3dx.ini", "d3d11.dll", "d3dcompiler_47.dll", "Run.exe"];
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+struct BackupEntry {
+    file: String,
+    existed: bool, // false: start_game created it, so restoring deletes it
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+#[serde(rename_all = "camelCase")]
+struct BackupManifest {
+    created_at: u64,
+    entries: Vec<BackupEntry>,
+}
+
+fn backup_dir(migoto_path: &Path) -> PathBuf {
+    migoto_path.join(BACKUP_DIR)
+}
+
+pub fn has_backup(migoto_path: &Path) -> bool {
+    backup_dir(migoto_path).join(MANIFEST_NAME).exists()
+}
+
+// Keeps an existing backup: it still holds the originals from a session that
+// was never restored, and the files on disk now are the launch-time versions.
+pub fn take_backup(migoto_path: &Path) -> Result<(), String> {
+    if has_backup(migoto_path) {
+        println!("[LaunchBackup] Keeping earlier backup in {:?}", backup_dir(migoto_path));
+        return Ok(());
+    }
+
+    let dir = backup_dir(migoto_path);
+    fs::create_dir_all(&dir).map_err(|e| format!("Failed to create launch backup folder: {}", e))?;
+
+    let mut manifest = BackupManifest {
+        created_at: std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or(0),
+        entries: Vec::new(),
+    };
+    for file in TRACKED_FILES {
+        let source = migoto_path.join(file);
+        let existed = source.is_file();
+        if existed {
+            fs::copy(&source, dir.join(file)).map_err(|e| format!("Failed to back up {}: {}", file, e))?;
+        }
+        manifest.entries.push(BackupEntry {
+            file: file.to_string(),
+            existed,
+        });
+    }
+
+    // The manifest goes last, so a half-written backup is never restored
+    let content = serde_json::to_string_pretty(&manifest)
+        .map_err(|e| format!("Failed to serialize launch backup manifest: {}", e))?;
+    fs::write(dir.join(MANIFEST_NAME), content).map_err(|e| format!("Failed to write launch backup manifest: {}", e))
+}
+
+// Puts every tracked file back and removes the backup. Returns the number of
+// files restored or removed; 0 when there was no backup.
+pub fn restore_backup(migoto_path: &Path) -> Result<usize, String> {
+    let dir = backup_dir(migoto_path);
+    let manifest_path = dir.join(MANIFEST_NAME);
+    if !manifest_path.exists() {
+        return Ok(0);
+    }
+
+    let content = fs::read_to_string(&manifest_path).map_err(|e| format!("Failed to read launch backup manifest: {}", e))?;
+    let manifest: BackupManifest =
+        serde_json::from_str(&content).map_err(|e| format!("Failed to parse launch backup manifest: {}", e))?;
+
+    let mut restored = 0;
+    for entry in &manifest.entries {
+        let target = migoto_path.join(&entry.file);
+        if entry.existed {
+            fs::copy(dir.join(&entry.file), &target).map_err(|e| {
+                format!("Failed to restore {}: {}. Please ensure the game is closed.", entry.file, e)
+            })?;
+            restored += 1;
+        } else if target.exists() {
+            fs::remove_file(&target).map_err(|e| {
+                format!("Failed to remove {}: {}. Please ensure the game is closed.", entry.file, e)
+            })?;
+            restored += 1;
+        }
+    }
+
+    fs::remove_dir_all(&dir).map_err(|e| format!("Failed to clean up launch backup: {}", e))?;
+    println!("[LaunchBackup] Restored {} file(s) in {:?}", restored, migoto_path);
+    Ok(restored)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn restore_puts_back_originals_and_removes_added_files() {
+        let dir = std::env::temp_dir().join(format!("ssmt4-launch-backup-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("d3dx.ini"), "[Loader]\ntarget = mine.exe\n").unwrap();
+        fs::write(dir.join("d3d11.dll"), b"user build").unwrap();
+
+        take_backup(&dir).unwrap();
+        fs::write(dir.join("d3dx.ini"), "[Loader]\ntarget = launched.exe\n").unwrap();
+        fs::write(dir.join("d3d11.dll"), b"bundled build").unwrap();
+        fs::write(dir.join("Run.exe"), b"bundled loader").unwrap();
+
+        // A second launch before restoring must not replace the originals
+        take_backup(&dir).unwrap();
+
+        assert_eq!(restore_backup(&dir).unwrap(), 3);
+        assert_eq!(fs::read_to_string(dir.join("d3dx.ini")).unwrap(), "[Loader]\ntarget = mine.exe\n");
+        assert_eq!(fs::read(dir.join("d3d11.dll")).unwrap(), b"user build");
+        assert!(!dir.join("Run.exe").exists());
+        assert!(!has_backup(&dir));
+        assert_eq!(restore_backup(&dir).unwrap(), 0);
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,460 @@
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use sysinfo::{ProcessRefreshKind, System, UpdateKind};
+
+// A process to start, independent of how the platform starts it
+#[derive(Debug, Clone, PartialEq)]
+pub struct LaunchCommand {
+    pub program: String,
+    pub args: Vec<String>,
+    pub working_dir: PathBuf,
+    pub env: Vec<(String, String)>,
+}
+
+impl LaunchCommand {
+    pub fn new(program: impl Into<String>, working_dir: impl Into<PathBuf>) -> Self {
+        Self {
+            program: program.into(),
+            args: Vec::new(),
+            working_dir: working_dir.into(),
+            env: Vec::new(),
+        }
+    }
+}
+
+pub trait ProcessLauncher: Send + Sync {
+    fn name(&self) -> &'static str;
+
+    // Whether 3Dmigoto is injected by its Run.exe loader. Launchers that load
+    // d3d11.dll through DLL overrides instead start the game directly.
+    fn uses_run_exe(&self) -> bool;
+
+    fn spawn(&self, cmd: &LaunchCommand) -> Result<(), String>;
+
+    fn is_process_running(&self, exe_path: &Path) -> bool {
+        find_process(exe_path)
+    }
+}
+
+// Match by file name, then by full exe path when readable. Elevated processes
+// (Run.exe after UAC) often hide their path from us, and under Wine the exe is
+// the preloader while the Windows path only shows up in the command line.
+pub fn find_process_pid(exe_path: &Path) -> Option<u32> {
+    let target_path = exe_path.to_string_lossy().to_lowercase();
+    let target_name = exe_path
+        .file_name()
+        .map(|n| n.to_string_lossy().to_lowercase())
+        .unwrap_or_default();
+
+    let mut sys = System::new();
+    sys.refresh_processes_specifics(
+        ProcessRefreshKind::new()
+            .with_exe(UpdateKind::Always)
+            .with_cmd(UpdateKind::Always),
+    );
+
+    sys.processes().iter().find_map(|(pid, process)| {
+        let proc_name = Path::new(process.name()).to_string_lossy().to_lowercase();
+        let cmd_name = process
+            .cmd()
+            .first()
+            .map(|c| c.replace('\\', "/"))
+            .and_then(|c| c.rsplit('/').next().map(|n| n.to_lowercase()));
+
+        if proc_name != target_name && cmd_name.as_deref() != Some(target_name.as_str()) {
+            return None;
+        }
+
+        let same_exe = match process.exe() {
+            Some(exe) => {
+                let exe_name = exe.file_name().map(|n| n.to_string_lossy().to_lowercase());
+                // A different copy of the same exe does not count, a wrapper does
+                exe.to_string_lossy().to_lowercase() == target_path || exe_name.as_deref() != Some(target_name.as_str())
+            }
+            None => true,
+        };
+        same_exe.then(|| pid.as_u32())
+    })
+}
+
+pub fn find_process(exe_path: &Path) -> bool {
+    find_process_pid(exe_path).is_some()
+}
+
+pub fn is_pid_running(pid: u32) -> bool {
+    let mut sys = System::new();
+    sys.refresh_process_specifics(sysinfo::Pid::from_u32(pid), ProcessRefreshKind::new())
+}
+
+// Splits launch args with shell-word rules: whitespace separates words, single
+// quotes are literal, double quotes group. A backslash only escapes a double
+// quote (or a single quote/space outside quotes), so Windows paths such as
+// C:\Games\Mods pass through untouched.
+pub fn split_args(input: &str) -> Result<Vec<String>, String> {
+    let mut args = Vec::new();
+    let mut current = String::new();
+    let mut in_word = false;
+    let mut quote: Option<char> = None;
+    let mut chars = input.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match (quote, c) {
+            (Some('\''), '\'') => quote = None,
+            (Some('\''), _) => current.push(c),
+            (Some('"'), '"') => quote = None,
+            (_, '\\') => {
+                let escapable = match chars.peek() {
+                    Some('"') => true,
+                    Some('\'') | Some(' ') | Some('\t') => quote.is_none(),
+                    _ => false,
+                };
+                if escapable {
+                    current.push(chars.next().unwrap());
+                } else {
+                    current.push('\\');
+                }
+                in_word = true;
+            }
+            (Some(_), _) => current.push(c),
+            (None, '"') | (None, '\'') => {
+                quote = Some(c);
+                in_word = true;
+            }
+            (None, c) if c.is_whitespace() => {
+                if in_word {
+                    args.push(std::mem::take(&mut current));
+                    in_word = false;
+                }
+            }
+            (None, _) => {
+                current.push(c);
+                in_word = true;
+            }
+        }
+    }
+
+    if let Some(q) = quote {
+        return Err(format!("Unterminated {} quote in launch arguments: {}", q, input));
+    }
+    if in_word {
+        args.push(current);
+    }
+    Ok(args)
+}
+
+// Quotes one argument so CommandLineToArgvW (and the MSVC runtime) reads it back unchanged
+pub fn quote_windows_arg(arg: &str) -> String {
+    if !arg.is_empty() && !arg.chars().any(|c| c == ' ' || c == '\t' || c == '"') {
+        return arg.to_string();
+    }
+
+    let mut quoted = String::from("\"");
+    let mut backslashes = 0;
+    for c in arg.chars() {
+        match c {
+            '\\' => backslashes += 1,
+            '"' => {
+                // Backslashes before a quote are doubled, plus one to escape the quote
+                quoted.push_str(&"\\".repeat(backslashes * 2 + 1));
+                quoted.push('"');
+                backslashes = 0;
+            }
+            _ => {
+                quoted.push_str(&"\\".repeat(backslashes));
+                quoted.push(c);
+                backslashes = 0;
+            }
+        }
+    }
+    // The closing quote must not be escaped by trailing backslashes
+    quoted.push_str(&"\\".repeat(backslashes * 2));
+    quoted.push('"');
+    quoted
+}
+
+pub fn windows_command_line(args: &[String]) -> String {
+    args.iter().map(|a| quote_windows_arg(a)).collect::<Vec<_>>().join(" ")
+}
+
+// The script never contains a path or argument: everything arrives through the
+// environment, so quotes in a game path cannot change what PowerShell runs.
+const START_PROCESS_SCRIPT: &str = "$p = @{ FilePath = $env:SSMT4_LAUNCH_PROGRAM; WorkingDirectory = $env:SSMT4_LAUNCH_DIR }; \
+if ($env:SSMT4_LAUNCH_ARGS) { $p.ArgumentList = $env:SSMT4_LAUNCH_ARGS }; \
+Start-Process @p";
+
+// Native Windows: Start-Process via PowerShell (fixes UAC focus issues)
+pub struct WindowsLauncher;
+
+impl WindowsLauncher {
+    pub fn build_command(&self, cmd: &LaunchCommand) -> (String, Vec<String>, Vec<(String, String)>) {
+        let args = vec![
+            "-NoProfile".to_string(),
+            "-NonInteractive".to_string(),
+            "-Command".to_string(),
+            START_PROCESS_SCRIPT.to_string(),
+        ];
+        let mut env = vec![
+            ("SSMT4_LAUNCH_PROGRAM".to_string(), cmd.program.clone()),
+            ("SSMT4_LAUNCH_DIR".to_string(), cmd.working_dir.to_string_lossy().to_string()),
+            // Start-Process joins an array with plain spaces, so pass one pre-quoted string
+            ("SSMT4_LAUNCH_ARGS".to_string(), windows_command_line(&cmd.args)),
+        ];
+        env.extend(cmd.env.iter().cloned());
+        ("powershell".to_string(), args, env)
+    }
+}
+
+impl ProcessLauncher for WindowsLauncher {
+    fn name(&self) -> &'static str {
+        "windows"
+    }
+
+    fn uses_run_exe(&self) -> bool {
+        true
+    }
+
+    fn spawn(&self, cmd: &LaunchCommand) -> Result<(), String> {
+        let (program, args, env) = self.build_command(cmd);
+        println!("[Launcher] Start-Process {:?} {:?} in {:?}", cmd.program, cmd.args, cmd.working_dir);
+
+        Command::new(&program)
+            .args(&args)
+            .envs(env)
+            .spawn()
+            .map_err(|e| format!("Failed to launch {}: {}", cmd.program, e))?;
+        Ok(())
+    }
+}
+
+// Wine or Proton on Linux. 3Dmigoto's d3d11.dll is picked up through a
+// native DLL override, so Run.exe is never started. The override only looks
+// next to the exe, see wine_stage for how the files get there.
+pub struct WineLauncher {
+    pub wine_binary: String,
+    pub prefix: Option<String>,
+}
+
+impl WineLauncher {
+    fn is_proton(&self) -> bool {
+        Path::new(&self.wine_binary)
+            .file_name()
+            .map(|n| n.to_string_lossy().eq_ignore_ascii_case("proton"))
+            .unwrap_or(false)
+    }
+
+    pub fn build_command(&self, cmd: &LaunchCommand) -> (String, Vec<String>, Vec<(String, String)>) {
+        let mut args = Vec::new();
+        let mut env = vec![("WINEDLLOVERRIDES".to_string(), "d3d11=n,b".to_string())];
+
+        if self.is_proton() {
+            args.push("run".to_string());
+            if let Some(prefix) = self.prefix.as_ref().filter(|p| !p.is_empty()) {
+                env.push(("STEAM_COMPAT_DATA_PATH".to_string(), prefix.clone()));
+            }
+            // Proton refuses to start without it; the Steam root is the usual value
+            if std::env::var("STEAM_COMPAT_CLIENT_INSTALL_PATH").is_err() {
+                if let Ok(home) = std::env::var("HOME") {
+                    env.push((
+                        "STEAM_COMPAT_CLIENT_INSTALL_PATH".to_string(),
+                        format!("{}/.steam/steam", home),
+                    ));
+                }
+            }
+        } else if let Some(prefix) = self.prefix.as_ref().filter(|p| !p.is_empty()) {
+            env.push(("WINEPREFIX".to_string(), prefix.clone()));
+        }
+
+        args.push(cmd.program.clone());
+        args.extend(cmd.args.iter().cloned());
+        env.extend(cmd.env.iter().cloned());
+
+        (self.wine_binary.clone(), args, env)
+    }
+}
+
+impl ProcessLauncher for WineLauncher {
+    fn name(&self) -> &'static str {
+        "wine"
+    }
+
+    fn uses_run_exe(&self) -> bool {
+        false
+    }
+
+    fn spawn(&self, cmd: &LaunchCommand) -> Result<(), String> {
+        let (program, args, env) = self.build_command(cmd);
+        println!("[Launcher] {} {:?}", program, args);
+
+        Command::new(&program)
+            .args(&args)
+            .current_dir(&cmd.working_dir)
+            .envs(env)
+            .spawn()
+            .map_err(|e| format!("Failed to launch {} via {}: {}", cmd.program, program, e))?;
+        Ok(())
+    }
+}
+
+pub fn default_launch_mode() -> &'static str {
+    if cfg!(windows) {
+        "windows"
+    } else {
+        "wine"
+    }
+}
+
+// launch_mode: "windows", "wine" or "proton"; empty picks the platform default
+pub fn create_launcher(
+    launch_mode: Option<&str>,
+    wine_binary: Option<&str>,
+    wine_prefix: Option<&str>,
+) -> Result<Box<dyn ProcessLauncher>, String> {
+    let mode = launch_mode.filter(|m| !m.trim().is_empty()).unwrap_or(default_launch_mode());
+    let wine_binary = wine_binary.filter(|b| !b.trim().is_empty());
+
+    match mode.to_lowercase().as_str() {
+        "windows" => Ok(Box::new(WindowsLauncher)),
+        "wine" => Ok(Box::new(WineLauncher {
+            wine_binary: wine_binary.unwrap_or("wine").to_string(),
+            prefix: wine_prefix.map(|p| p.to_string()),
+        })),
+        "proton" => Ok(Box::new(WineLauncher {
+            wine_binary: wine_binary
+                .ok_or("Proton launch mode needs the path to the proton script")?
+                .to_string(),
+            prefix: wine_prefix.map(|p| p.to_string()),
+        })),
+        other => Err(format!("Unknown launch mode: {}", other)),
+    }
+}
+
+// Records every spawn instead of starting anything
+#[cfg(test)]
+pub struct RecordingLauncher {
+    pub uses_run_exe: bool,
+    pub spawned: std::sync::Mutex<Vec<LaunchCommand>>,
+}
+
+#[cfg(test)]
+impl RecordingLauncher {
+    pub fn new(uses_run_exe: bool) -> Self {
+        Self {
+            uses_run_exe,
+            spawned: std::sync::Mutex::new(Vec::new()),
+        }
+    }
+
+    pub fn spawned(&self) -> Vec<LaunchCommand> {
+        self.spawned.lock().unwrap().clone()
+    }
+}
+
+#[cfg(test)]
+impl ProcessLauncher for RecordingLauncher {
+    fn name(&self) -> &'static str {
+        "recording"
+    }
+
+    fn uses_run_exe(&self) -> bool {
+        self.uses_run_exe
+    }
+
+    fn spawn(&self, cmd: &LaunchCommand) -> Result<(), String> {
+        self.spawned.lock().unwrap().push(cmd.clone());
+        Ok(())
+    }
+
+    // Pretend anything we spawned is up, so the Run.exe wait returns at once
+    fn is_process_running(&self, exe_path: &Path) -> bool {
+        let name = exe_path.file_name().map(|n| n.to_string_lossy().to_lowercase());
+        self.spawned
+            .lock()
+            .unwrap()
+            .iter()
+            .any(|c| Path::new(&c.program).file_name().map(|n| n.to_string_lossy().to_lowercase()) == name)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn wine_sets_prefix_and_dll_override() {
+        let launcher = WineLauncher {
+            wine_binary: "/usr/bin/wine".to_string(),
+            prefix: Some("/home/me/.wine-games".to_string()),
+        };
+        let mut cmd = LaunchCommand::new("C:\\Games\\Game.exe", "/games");
+        cmd.args.push("-popupwindow".to_string());
+
+        let (program, args, env) = launcher.build_command(&cmd);
+        assert_eq!(program, "/usr/bin/wine");
+        assert_eq!(args, vec!["C:\\Games\\Game.exe", "-popupwindow"]);
+        assert!(env.contains(&("WINEDLLOVERRIDES".to_string(), "d3d11=n,b".to_string())));
+        assert!(env.contains(&("WINEPREFIX".to_string(), "/home/me/.wine-games".to_string())));
+    }
+
+    #[test]
+    fn proton_uses_run_verb_and_compat_data_path() {
+        let launcher = WineLauncher {
+            wine_binary: "/steam/common/Proton 9.0/proton".to_string(),
+            prefix: Some("/steam/compatdata/123".to_string()),
+        };
+        let (_, args, env) = launcher.build_command(&LaunchCommand::new("Game.exe", "/games"));
+        assert_eq!(args, vec!["run", "Game.exe"]);
+        assert!(env.contains(&("STEAM_COMPAT_DATA_PATH".to_string(), "/steam/compatdata/123".to_string())));
+        assert!(!env.iter().any(|(k, _)| k == "WINEPREFIX"));
+    }
+
+    #[test]
+    fn split_args_follows_shell_word_rules() {
+        assert_eq!(split_args("").unwrap(), Vec::<String>::new());
+        assert_eq!(split_args("  -popupwindow   -screen-fullscreen 0 ").unwrap(), vec!["-popupwindow", "-screen-fullscreen", "0"]);
+        assert_eq!(split_args(r#"-log "C:\Program Files\Game\log.txt""#).unwrap(), vec!["-log", r"C:\Program Files\Game\log.txt"]);
+        assert_eq!(split_args(r#"-name 'Tom "T" Jones'"#).unwrap(), vec!["-name", r#"Tom "T" Jones"#]);
+        assert_eq!(split_args(r#"-title "say \"hi\"""#).unwrap(), vec!["-title", r#"say "hi""#]);
+        assert_eq!(split_args(r"C:\Games\My\ Game\x.exe").unwrap(), vec![r"C:\Games\My Game\x.exe"]);
+        assert_eq!(split_args(r#"-empty "" x"#).unwrap(), vec!["-empty", "", "x"]);
+        assert_eq!(split_args(r#"--path="D:\Mods Folder""#).unwrap(), vec![r"--path=D:\Mods Folder"]);
+        assert!(split_args("-name 'unterminated").is_err());
+        assert!(split_args("-name \"unterminated").is_err());
+    }
+
+    #[test]
+    fn windows_quoting_round_trips_tricky_args() {
+        assert_eq!(quote_windows_arg("-popupwindow"), "-popupwindow");
+        assert_eq!(quote_windows_arg(""), "\"\"");
+        assert_eq!(quote_windows_arg(r"C:\Program Files\Game"), r#""C:\Program Files\Game""#);
+        assert_eq!(quote_windows_arg(r"C:\Program Files\Game\"), r#""C:\Program Files\Game\\""#);
+        assert_eq!(quote_windows_arg(r#"say "hi""#), r#""say \"hi\"""#);
+        assert_eq!(quote_windows_arg(r#"a\"b"#), r#""a\\\"b""#);
+        assert_eq!(quote_windows_arg("O'Brien's Games"), "\"O'Brien's Games\"");
+        assert_eq!(
+            windows_command_line(&["-log".to_string(), r"D:\It's a Game\log.txt".to_string()]),
+            r#"-log "D:\It's a Game\log.txt""#
+        );
+    }
+
+    #[test]
+    fn windows_launcher_never_interpolates_paths_into_the_script() {
+        let mut cmd = LaunchCommand::new(r"D:\Games\O'Brien's $(Game)\Game.exe", r"D:\Games\O'Brien's $(Game)");
+        cmd.args = vec!["-name".to_string(), "it's \"quoted\"".to_string()];
+
+        let (program, args, env) = WindowsLauncher.build_command(&cmd);
+        assert_eq!(program, "powershell");
+        assert_eq!(args.last().map(String::as_str), Some(START_PROCESS_SCRIPT));
+        assert!(!args.iter().any(|a| a.contains("O'Brien")));
+        assert!(env.contains(&("SSMT4_LAUNCH_PROGRAM".to_string(), cmd.program.clone())));
+        assert!(env.contains(&("SSMT4_LAUNCH_DIR".to_string(), r"D:\Games\O'Brien's $(Game)".to_string())));
+        assert!(env.contains(&("SSMT4_LAUNCH_ARGS".to_string(), r#"-name "it's \"quoted\"""#.to_string())));
+    }
+
+    #[test]
+    fn create_launcher_rejects_unknown_mode() {
+        assert!(create_launcher(Some("dosbox"), None, None).is_err());
+        assert!(create_launcher(Some("proton"), None, None).is_err());
+        assert!(!create_launcher(Some("wine"), None, None).unwrap().uses_run_exe());
+        assert!(create_launcher(Some("windows"), None, None).unwrap().uses_run_exe());
+    }
+}
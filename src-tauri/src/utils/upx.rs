@@ -0,0 +1,165 @@
+use sha2::{Digest, Sha256};
+use std::fs;
+#[cfg(windows)]
+use std::os::windows::process::CommandExt;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+#[cfg(windows)]
+const CREATE_NO_WINDOW: u32 = 0x08000000;
+
+fn sha256_hex(path: &Path) -> Result<String, String> {
+    let bytes = fs::read(path).map_err(|e| format!("Failed to read {:?}: {}", path, e))?;
+    Ok(Sha256::digest(&bytes).iter().map(|b| format!("{:02x}", b)).collect())
+}
+
+// Runs upx.exe and turns a non-zero exit into an error carrying its output
+fn run_upx(upx_exe: &Path, args: &[&str], file: &Path) -> Result<(), String> {
+    let mut command = Command::new(upx_exe);
+    command.args(args).arg(file);
+    #[cfg(windows)]
+    command.creation_flags(CREATE_NO_WINDOW);
+
+    let output = command.output().map_err(|e| format!("Failed to execute UPX: {}", e))?;
+    if output.status.success() {
+        return Ok(());
+    }
+
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let detail = if stderr.trim().is_empty() { stdout.trim() } else { stderr.trim() };
+    Err(format!("upx {} exited with {:?}: {}", args.join(" "), output.status.code(), detail))
+}
+
+pub fn is_packed(upx_exe: &Path, dll_path: &Path) -> bool {
+    run_upx(upx_exe, &["-t", "-q"], dll_path).is_ok()
+}
+
+// Packs dll_path in place. Packed output is cached as <cache_dir>/<sha256 of the
+// unpacked dll>.dll, so the same d3d11.dll is only run through UPX once.
+// Returns true when the cached copy was reused.
+pub fn pack_cached(upx_exe: &Path, cache_dir: &Path, dll_path: &Path) -> Result<bool, String> {
+    let hash = sha256_hex(dll_path)?;
+    let cached = cache_dir.join(format!("{}.dll", hash));
+
+    if cached.is_file() {
+        fs::copy(&cached, dll_path).map_err(|e| format!("Failed to copy packed dll from cache: {}", e))?;
+        println!("[Upx] Reused cached pack {:?}", cached);
+        return Ok(true);
+    }
+
+    if is_packed(upx_exe, dll_path) {
+        println!("[Upx] {:?} is already packed", dll_path);
+        return Ok(false);
+    }
+
+    fs::create_dir_all(cache_dir).map_err(|e| format!("Failed to create UPX cache folder: {}", e))?;
+    let work = cache_dir.join(format!("{}.tmp", hash));
+    fs::copy(dll_path, &work).map_err(|e| format!("Failed to stage dll for UPX: {}", e))?;
+
+    // Only a pack that also passes upx -t makes it into the cache
+    let packed = run_upx(upx_exe, &["-q"], &work).and_then(|_| run_upx(upx_exe, &["-t", "-q"], &work));
+    if let Err(e) = packed {
+        let _ = fs::remove_file(&work);
+        return Err(e);
+    }
+
+    fs::rename(&work, &cached).map_err(|e| format!("Failed to store packed dll in cache: {}", e))?;
+    fs::copy(&cached, dll_path).map_err(|e| format!("Failed to copy packed dll: {}", e))?;
+    println!("[Upx] Packed {:?} and cached it as {:?}", dll_path, cached);
+    Ok(false)
+}
+
+pub fn unpack(upx_exe: &Path, dll_path: &Path) -> Result<(), String> {
+    if !is_packed(upx_exe, dll_path) {
+        return Err(format!("{:?} is not packed with UPX", dll_path));
+    }
+    run_upx(upx_exe, &["-d", "-q"], dll_path)?;
+    println!("[Upx] Unpacked {:?}", dll_path);
+    Ok(())
+}
+
+pub fn cache_dir(global_games_dir: &Path) -> PathBuf {
+    global_games_dir
+        .parent()
+        .map(|p| p.to_path_buf())
+        .unwrap_or_else(|| global_games_dir.to_path_buf())
+        .join("UpxCache")
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use std::os::unix::fs::PermissionsExt;
+
+    // Stand-in for upx.exe: "packing" prepends UPX!, -t checks for it, -d strips it.
+    // Every run is counted in calls.txt. broken_pack writes output that fails -t.
+    fn fake_upx(dir: &Path, broken_pack: bool) -> PathBuf {
+        let pack = if broken_pack { "printf 'garbage' > \"$f\"" } else { "{ printf 'UPX!'; cat \"$f\"; } > \"$f.new\" && mv \"$f.new\" \"$f\"" };
+        let script = format!(
+            "#!/bin/sh\necho run >> \"{calls}\"\nfor a in \"$@\"; do f=\"$a\"; done\ncase \"$1\" in\n  -t) head -c 4 \"$f\" | grep -q 'UPX!' ;;\n  -d) tail -c +5 \"$f\" > \"$f.new\" && mv \"$f.new\" \"$f\" ;;\n  *) {pack} ;;\nesac\n",
+            calls = dir.join("calls.txt").display(),
+            pack = pack
+        );
+        let path = dir.join("upx");
+        fs::write(&path, script).unwrap();
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o755)).unwrap();
+        path
+    }
+
+    fn calls(dir: &Path) -> usize {
+        fs::read_to_string(dir.join("calls.txt")).map(|s| s.lines().count()).unwrap_or(0)
+    }
+
+    fn setup(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("ssmt4-upx-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(dir.join("game")).unwrap();
+        fs::write(dir.join("game/d3d11.dll"), b"MZ dll").unwrap();
+        dir
+    }
+
+    #[test]
+    fn packs_once_per_dll_content_and_reuses_the_cache() {
+        let dir = setup("cache");
+        let upx = fake_upx(&dir, false);
+        let dll = dir.join("game/d3d11.dll");
+        let cache = dir.join("cache");
+
+        assert!(!pack_cached(&upx, &cache, &dll).unwrap());
+        assert_eq!(fs::read(&dll).unwrap(), b"UPX!MZ dll");
+        let hash = Sha256::digest(b"MZ dll").iter().map(|b| format!("{:02x}", b)).collect::<String>();
+        assert!(cache.join(format!("{}.dll", hash)).is_file());
+        let runs = calls(&dir);
+
+        // Same unpacked dll again (copy_boot_files puts the plain one back each launch)
+        fs::write(&dll, b"MZ dll").unwrap();
+        assert!(pack_cached(&upx, &cache, &dll).unwrap());
+        assert_eq!(fs::read(&dll).unwrap(), b"UPX!MZ dll");
+        assert_eq!(calls(&dir), runs);
+
+        // A different build gets its own cache entry
+        fs::write(&dll, b"MZ newer dll").unwrap();
+        assert!(!pack_cached(&upx, &cache, &dll).unwrap());
+        assert_eq!(fs::read_dir(&cache).unwrap().count(), 2);
+
+        unpack(&upx, &dll).unwrap();
+        assert_eq!(fs::read(&dll).unwrap(), b"MZ newer dll");
+        assert!(unpack(&upx, &dll).is_err());
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn pack_that_fails_the_test_leaves_the_dll_unpacked_and_uncached() {
+        let dir = setup("broken");
+        let upx = fake_upx(&dir, true);
+        let dll = dir.join("game/d3d11.dll");
+        let cache = dir.join("cache");
+
+        assert!(pack_cached(&upx, &cache, &dll).is_err());
+        assert_eq!(fs::read(&dll).unwrap(), b"MZ dll");
+        assert_eq!(fs::read_dir(&cache).unwrap().count(), 0);
+        assert!(!is_packed(&upx, &dll));
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,181 @@
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+// Wine only honours the d3d11=n override for a d3d11.dll in the exe's own
+// folder, and 3Dmigoto then reads d3dx.ini (and the folders it includes)
+// relative to that dll. So for a Wine/Proton launch the 3Dmigoto files are
+// linked into the game folder, and removed again after the game exits.
+const MANIFEST_NAME: &str = ".ssmt4-wine-staged.json";
+const STAGED_FILES: [&str; 4] = ["d3d11.dll", "d3dcompiler_47.dll", "d3dx.ini", "d3dx_user.ini"];
+// Folders of the 3Dmigoto dir that only SSMT4 uses
+const SKIPPED_DIRS: [&str; 1] = ["SSMT4Backups"];
+
+#[derive(Debug, Serialize, Deserialize, Default)]
+struct StageManifest {
+    entries: Vec<String>, // Names created in the game folder
+}
+
+fn same_dir(a: &Path, b: &Path) -> bool {
+    match (fs::canonicalize(a), fs::canonicalize(b)) {
+        (Ok(a), Ok(b)) => a == b,
+        _ => a == b,
+    }
+}
+
+// The names to stage: the dlls and ini files, plus every folder d3dx.ini may include
+fn stage_names(migoto_path: &Path) -> Vec<String> {
+    let mut names: Vec<String> = STAGED_FILES
+        .iter()
+        .filter(|f| migoto_path.join(f).is_file())
+        .map(|f| f.to_string())
+        .collect();
+    if let Ok(entries) = fs::read_dir(migoto_path) {
+        for entry in entries.flatten() {
+            let name = entry.file_name().to_string_lossy().to_string();
+            if entry.path().is_dir() && !SKIPPED_DIRS.iter().any(|s| s.eq_ignore_ascii_case(&name)) {
+                names.push(name);
+            }
+        }
+    }
+    names
+}
+
+#[cfg(unix)]
+fn link(source: &Path, dest: &Path) -> std::io::Result<()> {
+    std::os::unix::fs::symlink(source, dest)
+}
+
+// No symlinks without developer mode; folders are left out there
+#[cfg(not(unix))]
+fn link(source: &Path, dest: &Path) -> std::io::Result<()> {
+    if source.is_dir() {
+        return Ok(());
+    }
+    fs::copy(source, dest).map(|_| ())
+}
+
+fn is_staged_entry(path: &Path) -> bool {
+    match fs::symlink_metadata(path) {
+        Ok(meta) => meta.file_type().is_symlink() || (cfg!(not(unix)) && meta.is_file()),
+        Err(_) => false,
+    }
+}
+
+// Whether the game folder has a real d3d11.dll of its own that would be loaded
+// instead of 3Dmigoto's. Links left by an earlier stage do not count.
+pub fn has_foreign_d3d11(migoto_path: &Path, game_dir: &Path) -> bool {
+    if same_dir(migoto_path, game_dir) {
+        return false;
+    }
+    let dll = game_dir.join("d3d11.dll");
+    dll.exists() && !fs::symlink_metadata(&dll).map(|m| m.file_type().is_symlink()).unwrap_or(false)
+}
+
+// Links the 3Dmigoto files into game_dir. Names that already exist there are
+// left alone. Returns the number of entries created.
+pub fn stage(migoto_path: &Path, game_dir: &Path) -> Result<usize, String> {
+    if same_dir(migoto_path, game_dir) {
+        return Ok(0);
+    }
+    if !game_dir.is_dir() {
+        return Err(format!("Game folder not found: {:?}", game_dir));
+    }
+    // Whatever an earlier session left behind
+    unstage(game_dir)?;
+
+    let mut manifest = StageManifest::default();
+    let source_root = fs::canonicalize(migoto_path).unwrap_or_else(|_| migoto_path.to_path_buf());
+    for name in stage_names(migoto_path) {
+        let dest = game_dir.join(&name);
+        if fs::symlink_metadata(&dest).is_ok() {
+            println!("[WineStage] {:?} already exists, not linking it", dest);
+            continue;
+        }
+        link(&source_root.join(&name), &dest).map_err(|e| format!("Failed to link {} into the game folder: {}", name, e))?;
+        manifest.entries.push(name);
+        // Written after every entry, so a failure halfway can still be undone
+        let content = serde_json::to_string_pretty(&manifest)
+            .map_err(|e| format!("Failed to serialize Wine stage manifest: {}", e))?;
+        fs::write(game_dir.join(MANIFEST_NAME), content)
+            .map_err(|e| format!("Failed to write Wine stage manifest: {}", e))?;
+    }
+
+    println!("[WineStage] Linked {} entries into {:?}", manifest.entries.len(), game_dir);
+    Ok(manifest.entries.len())
+}
+
+// Removes what stage() created. Returns the number of entries removed.
+pub fn unstage(game_dir: &Path) -> Result<usize, String> {
+    let manifest_path = game_dir.join(MANIFEST_NAME);
+    let Ok(content) = fs::read_to_string(&manifest_path) else {
+        return Ok(0);
+    };
+    let manifest: StageManifest =
+        serde_json::from_str(&content).map_err(|e| format!("Failed to parse Wine stage manifest: {}", e))?;
+
+    let mut removed = 0;
+    for name in &manifest.entries {
+        let path: PathBuf = game_dir.join(name);
+        // Something the user put there since is not ours to delete
+        if !is_staged_entry(&path) {
+            continue;
+        }
+        fs::remove_file(&path)
+            .or_else(|_| fs::remove_dir(&path))
+            .map_err(|e| format!("Failed to remove {:?}: {}. Please ensure the game is closed.", path, e))?;
+        removed += 1;
+    }
+    fs::remove_file(&manifest_path).map_err(|e| format!("Failed to remove Wine stage manifest: {}", e))?;
+    println!("[WineStage] Removed {} linked entries from {:?}", removed, game_dir);
+    Ok(removed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn stage_links_3dmigoto_files_next_to_the_exe_and_unstage_removes_them() {
+        let root = std::env::temp_dir().join(format!("ssmt4-wine-stage-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+        let migoto = root.join("3Dmigoto");
+        let game = root.join("Game");
+        fs::create_dir_all(migoto.join("Mods/Ayaka")).unwrap();
+        fs::create_dir_all(migoto.join("SSMT4Backups")).unwrap();
+        fs::create_dir_all(&game).unwrap();
+        fs::write(migoto.join("d3d11.dll"), b"3dmigoto").unwrap();
+        fs::write(migoto.join("d3dx.ini"), "[Include]\ninclude_recursive = Mods\n").unwrap();
+        fs::write(migoto.join("Run.exe"), b"").unwrap();
+        fs::write(game.join("Game.exe"), b"").unwrap();
+        fs::write(game.join("d3dcompiler_47.dll"), b"game's own").unwrap();
+        fs::write(migoto.join("d3dcompiler_47.dll"), b"3dmigoto").unwrap();
+
+        assert!(!has_foreign_d3d11(&migoto, &game));
+        stage(&migoto, &game).unwrap();
+        assert_eq!(fs::read(game.join("d3d11.dll")).unwrap(), b"3dmigoto");
+        assert!(fs::read_to_string(game.join("d3dx.ini")).unwrap().contains("include_recursive"));
+        assert!(!game.join("Run.exe").exists());
+        assert!(!game.join("SSMT4Backups").exists());
+        assert_eq!(fs::read(game.join("d3dcompiler_47.dll")).unwrap(), b"game's own");
+        if cfg!(unix) {
+            assert!(game.join("Mods/Ayaka").is_dir());
+        }
+        // Linked, not a second copy of the game's real d3d11.dll
+        assert!(!has_foreign_d3d11(&migoto, &game));
+
+        unstage(&game).unwrap();
+        assert!(!game.join("d3d11.dll").exists());
+        assert!(!game.join("d3dx.ini").exists());
+        assert!(!game.join("Mods").exists());
+        assert!(!game.join(MANIFEST_NAME).exists());
+        assert_eq!(fs::read(game.join("d3dcompiler_47.dll")).unwrap(), b"game's own");
+        assert!(migoto.join("Mods/Ayaka").is_dir());
+        assert_eq!(fs::read(migoto.join("d3d11.dll")).unwrap(), b"3dmigoto");
+
+        fs::write(game.join("d3d11.dll"), b"reshade").unwrap();
+        assert!(has_foreign_d3d11(&migoto, &game));
+        assert_eq!(stage(&migoto, &migoto).unwrap(), 0);
+        let _ = fs::remove_dir_all(&root);
+    }
+}
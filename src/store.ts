@@ -40,6 +40,10 @@ export interface GameInfo {
   bgVideoPath?: string;
   bgType: BGType;
   showSidebar: boolean;
+  favorite: boolean;
+  categories: string[];
+  lastPlayedAt?: number | null; // Unix seconds
+  order?: number | null; // Sidebar position, unset until the user reorders
 }
 
 const defaultSettings: AppSettings = {
@@ -115,6 +119,10 @@ export async function loadGames() {
         bgVideoPath: g.bgVideoPath ? convertFileSrc(g.bgVideoPath) + `?t=${timestamp}` : undefined,
         bgType: g.bgType || BGType.Image,
         showSidebar: g.showSidebar,
+        favorite: g.favorite || false,
+        categories: g.categories || [],
+        lastPlayedAt: g.lastPlayedAt,
+        order: g.order,
       } as GameInfo;
     });
 